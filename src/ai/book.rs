@@ -0,0 +1,267 @@
+//! Opening books, which contain good moves for the start of the game, so the engines neither have
+//! to search for them nor play them badly.
+//!
+//! A book file has one entry per line. Empty lines and lines starting with # are ignored. An entry
+//! is either a transcript, optionally followed by a weight, e.g. "f5d6c3d3c4 3", which adds every
+//! move of the line with the weight, or a position, followed by a move and its weight, e.g.
+//! "---------------------------WB------BW--------------------------- B f5 10". Transcripts always
+//! start on the standard board, while positions can be of any size. Boards with blocked squares
+//! are left out, since their openings depend on the layout. The moves of a position are chosen
+//! with a probability proportional to their weight.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use board::{Bitboard, Board, Piece, Status};
+use rng::Rng;
+use square::Square;
+use symmetry::{Symmetry, canonical_pieces};
+use transcript::{ReplayError, Transcript};
+use ai::Engine;
+
+#[derive(Debug)]
+pub enum Error {
+	IO(io::Error),
+	/// The line with the number could not be read. The string describes the problem.
+	Parse(usize, String)
+}
+
+/// A move of the book, with the weight it is chosen with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookMove {
+	pub square: (u8, u8),
+	pub weight: u32
+}
+
+/// The key of the position in the book and the symmetry that turns the board into it. All
+/// equivalent positions share the same key, see symmetry::canonical.
+fn book_key(board: &Board) -> ((u8, Bitboard, Bitboard), Symmetry) {
+	let turn = board.turn();
+	let ((own, opp), symmetry) = canonical_pieces(board.pieces(turn), board.pieces(turn.opposite()), board.size());
+	((board.size(), own, opp), symmetry)
+}
+
+pub struct Book {
+	// The moves of every position, oriented as the position of the key, which holds the pieces of
+	// the player to move after the size of the board.
+	positions: HashMap<(u8, Bitboard, Bitboard), Vec<BookMove>>
+}
+
+impl Book {
+	pub fn new() -> Book {
+		Book {
+			positions: HashMap::new()
+		}
+	}
+
+	/// Read the book from the file at the path.
+	pub fn load(path: &str) -> Result<Book, Error> {
+		let file = match File::open(path) {
+			Ok(file) => file,
+			Err(err) => return Err(Error::IO(err))
+		};
+
+		let mut book = Book::new();
+		for (i, line) in BufReader::new(file).lines().enumerate() {
+			let line = match line {
+				Ok(line) => line,
+				Err(err) => return Err(Error::IO(err))
+			};
+
+			if let Err(err) = book.add_line(&line) {
+				return Err(Error::Parse(i + 1, err));
+			}
+		}
+
+		Ok(book)
+	}
+
+	/// Add a line of a book file to the book.
+	fn add_line(&mut self, line: &str) -> Result<(), String> {
+		let parts: Vec<&str> = line.split_whitespace().collect();
+		if parts.is_empty() || parts[0].starts_with('#') {
+			return Ok(());
+		}
+
+		// Positions are written without any digits, so they cannot be mistaken for a transcript.
+		if !parts[0].chars().any(|c| { c.is_digit(10) }) {
+			if parts.len() != 4 {
+				return Err("Expected a position, the piece to move, a move and a weight.".to_string());
+			}
+
+			let board: Board = match format!("{} {}", parts[0], parts[1]).parse() {
+				Ok(board) => board,
+				Err(err) => return Err(format!("Invalid position. {:?}", err))
+			};
+			let square: Square = match parts[2].parse() {
+				Ok(square) => square,
+				Err(err) => return Err(format!("Invalid move. {:?}", err))
+			};
+			let weight = match parts[3].parse() {
+				Ok(weight) => weight,
+				Err(err) => return Err(format!("Invalid weight. {}", err))
+			};
+
+			if !board.can_place(square.pos(), board.turn()) {
+				return Err(format!("{} is not a legal move in the position.", square));
+			}
+
+			self.add(&board, square.pos(), weight);
+			Ok(())
+		}
+		else {
+			if parts.len() > 2 {
+				return Err("Expected a transcript and optionally a weight.".to_string());
+			}
+
+			let transcript: Transcript = match parts[0].parse() {
+				Ok(transcript) => transcript,
+				Err(err) => return Err(format!("Invalid transcript. {:?}", err))
+			};
+			let weight = match parts.get(1) {
+				Some(w) => match w.parse() {
+					Ok(weight) => weight,
+					Err(err) => return Err(format!("Invalid weight. {}", err))
+				},
+				None => 1
+			};
+
+			match self.add_line_of_play(&transcript, transcript.len(), weight) {
+				Ok(()) => Ok(()),
+				Err(err) => Err(format!("Could not replay transcript. {:?}", err))
+			}
+		}
+	}
+
+	/// Add the move on the board to the book. If the move is already in the book, the weights are
+	/// added up. Boards with blocked squares are ignored.
+	pub fn add(&mut self, board: &Board, square: (u8, u8), weight: u32) {
+		if board.blocked() != 0 {
+			return;
+		}
+
+		let (key, symmetry) = book_key(board);
+		let square = symmetry.square(square, board.size());
+
+		let moves = self.positions.entry(key).or_insert(Vec::new());
+		for m in moves.iter_mut() {
+			if m.square == square {
+				m.weight += weight;
+				return;
+			}
+		}
+
+		moves.push(BookMove {
+			square: square,
+			weight: weight
+		});
+	}
+
+	/// Add the first `depth` moves of the transcript to the book, each with the weight provided.
+	pub fn add_line_of_play(&mut self, transcript: &Transcript, depth: usize, weight: u32) -> Result<(), ReplayError> {
+		let mut board = Board::new();
+		for (i, m) in transcript.moves().iter().take(depth).enumerate() {
+			// Passes are made by the board automatically.
+			let square = match m {
+				&Some(square) => square,
+				&None => continue
+			};
+
+			if !board.can_place(square.pos(), board.turn()) {
+				return Err(ReplayError::IllegalMove(i, square));
+			}
+
+			self.add(&board, square.pos(), weight);
+			let turn = board.turn();
+			board.place(square.pos(), turn).expect("The move has been checked to be legal.");
+		}
+
+		Ok(())
+	}
+
+	/// All moves of the book for the board.
+	pub fn moves(&self, board: &Board) -> Vec<BookMove> {
+		if board.blocked() != 0 {
+			return Vec::new();
+		}
+
+		let (key, symmetry) = book_key(board);
+
+		match self.positions.get(&key) {
+			Some(moves) => moves.iter().map(|m| {
+				BookMove {
+					square: symmetry.inverse().square(m.square, board.size()),
+					weight: m.weight
+				}
+			}).collect(),
+			None => Vec::new()
+		}
+	}
+
+	/// Choose one of the moves of the book for the board at random, with the more heavily weighted
+	/// ones being more likely. Returns None in case the book does not know the position.
+	pub fn choose(&self, board: &Board, rng: &mut Rng) -> Option<(u8, u8)> {
+		let moves = self.moves(board);
+		let total: u64 = moves.iter().map(|m| { m.weight as u64 }).sum();
+		if total == 0 {
+			return None;
+		}
+
+		let mut roll = rng.below(total);
+		for m in &moves {
+			if roll < m.weight as u64 {
+				return Some(m.square);
+			}
+
+			roll -= m.weight as u64;
+		}
+
+		None
+	}
+
+	/// The number of positions in the book.
+	pub fn len(&self) -> usize {
+		self.positions.len()
+	}
+
+	/// Write the book as a book file, one line for every move of every position. The positions
+	/// are written in their canonical form, with black to move.
+	pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		for (&(size, own, opp), moves) in &self.positions {
+			let position = Board::from_pieces(size, own, opp, Piece::Black);
+			for m in moves {
+				writeln!(out, "{} {} {}", position, Square::from(m.square), m.weight)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Lets the engine play, but takes the moves from the book for as long as it knows them.
+pub struct BookEngine<E: Engine> {
+	book: Book,
+	engine: E,
+	rng: Rng
+}
+
+impl<E: Engine> BookEngine<E> {
+	pub fn new(book: Book, engine: E) -> BookEngine<E> {
+		BookEngine {
+			book: book,
+			engine: engine,
+			rng: Rng::from_time()
+		}
+	}
+}
+
+impl<E: Engine> Engine for BookEngine<E> {
+	fn choose_move(&mut self, board: &Board) -> Option<(u8, u8)> {
+		if let Status::InProgress { .. } = board.status() {
+			if let Some(m) = self.book.choose(board, &mut self.rng) {
+				return Some(m);
+			}
+		}
+
+		self.engine.choose_move(board)
+	}
+}
@@ -0,0 +1,154 @@
+use std::i32;
+use board::{Bitboard, Board, Status, bit_square, board_mask, flipped, legal_moves, shift};
+
+// The four quadrants of a board with the side length provided. The number of empty squares in
+// each of them is used to decide which moves to try first.
+fn quadrants(size: u8) -> [Bitboard; 4] {
+	let half = size as i8 / 2;
+	let upper_left = board_mask(half as u8);
+	[upper_left, shift(upper_left, (half, 0)), shift(upper_left, (0, half)), shift(upper_left, (half, half))]
+}
+
+// Below this number of empty squares, sorting the moves by the mobility of the opponent costs
+// more than it saves.
+const FASTEST_FIRST_EMPTIES: u32 = 7;
+
+/// The perfect play in the endgame, as calculated by the EndgameSolver.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Solution {
+	/// The move that leads to the best result, or None if the player cannot place a piece.
+	pub best: Option<(u8, u8)>,
+	/// The difference in pieces at the end of the game, if both players play perfectly, from the
+	/// view of the player whos turn it is. Positive values mean the player wins.
+	pub diff: i32,
+	/// The number of positions that have been visited.
+	pub nodes: u64
+}
+
+/// Solves the end of the game exactly by searching every move until the board is full or neither
+/// player can move any more.
+pub struct EndgameSolver {
+	max_empties: u8,
+	nodes: u64,
+	// The squares and the quadrants of the board that is being solved.
+	area: Bitboard,
+	quadrants: [Bitboard; 4]
+}
+
+impl EndgameSolver {
+	/// Create a solver that takes positions with at most `max_empties` empty squares. Every
+	/// additional empty square makes the search a few times slower, so this should not be much
+	/// more than 20.
+	pub fn new(max_empties: u8) -> EndgameSolver {
+		EndgameSolver {
+			max_empties: max_empties,
+			nodes: 0,
+			area: 0,
+			quadrants: [0; 4]
+		}
+	}
+
+	/// The highest number of empty squares this solver takes.
+	pub fn max_empties(&self) -> u8 {
+		self.max_empties
+	}
+
+	/// Find the best move and the final result for the player whos turn it is. Returns None in
+	/// case there are too many empty squares.
+	pub fn solve(&mut self, board: &Board) -> Option<Solution> {
+		if board.empty_count() > self.max_empties {
+			return None;
+		}
+
+		self.nodes = 0;
+		self.area = board.area();
+		self.quadrants = quadrants(board.size());
+		let turn = board.turn();
+		let (own, opp) = (board.pieces(turn), board.pieces(turn.opposite()));
+
+		let (best, diff) = match board.status() {
+			Status::InProgress { .. } => {
+				let mut best = None;
+				let mut alpha = -i32::MAX;
+				for m in self.ordered_moves(own, opp, legal_moves(own, opp, self.area)) {
+					let flips = flipped(m, own, opp);
+					let diff = -self.negamax(opp & !flips, own | flips | m, -i32::MAX, -alpha);
+
+					if best.is_none() || diff > alpha {
+						best = Some(bit_square(m.trailing_zeros()));
+						alpha = diff;
+					}
+				}
+
+				(best, alpha)
+			},
+			Status::MustPass => (None, -self.negamax(opp, own, -i32::MAX, i32::MAX)),
+			Status::Finished { .. } => (None, own.count_ones() as i32 - opp.count_ones() as i32)
+		};
+
+		Some(Solution {
+			best: best,
+			diff: diff,
+			nodes: self.nodes
+		})
+	}
+
+	/// The final difference in pieces for the player owning `own`, if it is their turn.
+	fn negamax(&mut self, own: Bitboard, opp: Bitboard, mut alpha: i32, beta: i32) -> i32 {
+		self.nodes += 1;
+
+		let moves = legal_moves(own, opp, self.area);
+		if moves == 0 {
+			// The game is over when neither player can move, otherwise the player has to pass.
+			if legal_moves(opp, own, self.area) == 0 {
+				return own.count_ones() as i32 - opp.count_ones() as i32;
+			}
+
+			return -self.negamax(opp, own, -beta, -alpha);
+		}
+
+		let mut best = -i32::MAX;
+		for m in self.ordered_moves(own, opp, moves) {
+			let flips = flipped(m, own, opp);
+			let diff = -self.negamax(opp & !flips, own | flips | m, -beta, -alpha);
+
+			if diff > best {
+				best = diff;
+			}
+			if diff > alpha {
+				alpha = diff;
+			}
+			if alpha >= beta {
+				break;
+			}
+		}
+
+		best
+	}
+
+	/// Sort the moves, each as the bit of its square. Moves into quadrants with an odd number of
+	/// empty squares come first, since the player who moves last in a region usually gains from
+	/// it. While there are enough empty squares, the moves that leave the opponent with the fewest
+	/// answers are tried first.
+	fn ordered_moves(&self, own: Bitboard, opp: Bitboard, mut moves: Bitboard) -> Vec<Bitboard> {
+		let empty = self.area & !(own | opp);
+		let mut ordered: Vec<(u32, Bitboard)> = Vec::with_capacity(moves.count_ones() as usize);
+
+		while moves != 0 {
+			let m = moves & moves.wrapping_neg();
+			moves &= moves - 1;
+
+			let odd = self.quadrants.iter().any(|&q| { q & m != 0 && (q & empty).count_ones() % 2 == 1 });
+			let mut key = if odd { 0 } else { 1 };
+			if empty.count_ones() > FASTEST_FIRST_EMPTIES {
+				let flips = flipped(m, own, opp);
+				key += 2 * legal_moves(opp & !flips, own | flips | m, self.area).count_ones();
+			}
+
+			ordered.push((key, m));
+		}
+
+		ordered.sort_by_key(|&(key, _)| { key });
+		ordered.into_iter().map(|(_, m)| { m }).collect()
+	}
+}
@@ -0,0 +1,92 @@
+use analysis::{frontier, stable_pieces};
+use board::{Bitboard, Board, Piece, bit_square};
+
+/// Judges how good a position is for the player whos turn it is. The higher the score, the better
+/// the position. The scores should stay well below WIN_SCORE, so that a won game is always
+/// preferred.
+pub trait Evaluator: Send {
+	fn evaluate(&self, board: &Board) -> i32;
+}
+
+/// The value of each square of the 8x8 board, row by row. The corners can never be flipped, so
+/// they are the most valuable, while the squares next to them make it easy for the opponent to
+/// take the corner.
+pub const SQUARE_WEIGHTS: [i32; 64] = [
+	100, -20,  10,   5,   5,  10, -20, 100,
+	-20, -50,  -2,  -2,  -2,  -2, -50, -20,
+	 10,  -2,   1,   1,   1,   1,  -2,  10,
+	  5,  -2,   1,   0,   0,   1,  -2,   5,
+	  5,  -2,   1,   0,   0,   1,  -2,   5,
+	 10,  -2,   1,   1,   1,   1,  -2,  10,
+	-20, -50,  -2,  -2,  -2,  -2, -50, -20,
+	100, -20,  10,   5,   5,  10, -20, 100
+];
+
+/// The weight of the square on a board with the side length provided. Weights are given for the
+/// 8x8 board, so on other sizes every square takes the weight of the square that is as far from
+/// the nearest corner, with the middle of larger boards counting like the middle of the 8x8 one.
+pub fn square_weight(weights: &[i32; 64], size: u8, (x, y): (u8, u8)) -> i32 {
+	let dx = x.min(size - 1 - x).min(3) as usize;
+	let dy = y.min(size - 1 - y).min(3) as usize;
+	weights[dy * 8 + dx]
+}
+
+/// Sum up the weights of all the squares in the bitboard of a board with the side length provided.
+pub fn weight_sum(weights: &[i32; 64], mut bits: Bitboard, size: u8) -> i32 {
+	let mut sum = 0;
+	while bits != 0 {
+		sum += square_weight(weights, size, bit_square(bits.trailing_zeros()));
+		bits &= bits - 1;
+	}
+
+	sum
+}
+
+/// Simply counts the pieces. This plays very badly until the very end of the game.
+pub struct PieceCount;
+
+impl Evaluator for PieceCount {
+	fn evaluate(&self, board: &Board) -> i32 {
+		let turn = board.turn();
+		board.count(turn) as i32 - board.count(turn.opposite()) as i32
+	}
+}
+
+/// Judges the position by the squares the pieces are on, by how many moves both players have and
+/// by how many of their pieces are exposed or stable.
+pub struct Positional {
+	/// The weights of the squares of the 8x8 board, see square_weight.
+	pub weights: [i32; 64],
+	/// The value of every move the player has more than their opponent.
+	pub mobility: i32,
+	/// The value of every frontier piece the player has less than their opponent.
+	pub frontier: i32,
+	/// The value of every stable piece the player has more than their opponent.
+	pub stability: i32
+}
+
+impl Positional {
+	pub fn new() -> Positional {
+		Positional {
+			weights: SQUARE_WEIGHTS,
+			mobility: 5,
+			frontier: 3,
+			stability: 10
+		}
+	}
+}
+
+impl Evaluator for Positional {
+	fn evaluate(&self, board: &Board) -> i32 {
+		let (own, opp): (Piece, Piece) = (board.turn(), board.turn().opposite());
+		let (own_pieces, opp_pieces) = (board.pieces(own), board.pieces(opp));
+		let (size, area) = (board.size(), board.area());
+
+		let position = weight_sum(&self.weights, own_pieces, size) - weight_sum(&self.weights, opp_pieces, size);
+		let mobility = board.legal_moves(own).count_ones() as i32 - board.legal_moves(opp).count_ones() as i32;
+		let frontier = frontier(opp_pieces, own_pieces, area).count_ones() as i32 - frontier(own_pieces, opp_pieces, area).count_ones() as i32;
+		let stability = stable_pieces(own_pieces, opp_pieces, area).count_ones() as i32 - stable_pieces(opp_pieces, own_pieces, area).count_ones() as i32;
+
+		position + self.mobility * mobility + self.frontier * frontier + self.stability * stability
+	}
+}
@@ -0,0 +1,414 @@
+//! Monte Carlo tree search. Instead of judging positions with an evaluator, the engine plays many
+//! games to the end at random and prefers the moves that have won most of them. The tree is grown
+//! towards the moves that look best, while UCT makes sure the others are not forgotten entirely.
+use std::thread;
+use std::time::{Duration, Instant};
+use board::{Bitboard, Board, Piece, Status, bit_square, flipped, legal_moves, square_bit};
+use rng::Rng;
+use ai::Engine;
+
+/// How much unexplored moves are preferred over those that have won often so far.
+const EXPLORATION: f64 = 1.4;
+
+// Every corner of a board with the side length provided, with the square diagonally next to it.
+// The guided playouts always take corners when they can, while placing a piece next to a corner
+// that is still empty usually gives the corner away.
+fn x_squares(size: u8) -> [(Bitboard, Bitboard); 4] {
+	let last = size - 1;
+	[
+		(square_bit((0, 0)), square_bit((1, 1))),
+		(square_bit((last, 0)), square_bit((last - 1, 1))),
+		(square_bit((0, last)), square_bit((1, last - 1))),
+		(square_bit((last, last)), square_bit((last - 1, last - 1)))
+	]
+}
+
+/// The way the moves of the playouts are chosen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Playout {
+	/// Every legal move is equally likely.
+	Random,
+	/// Corners are always taken, the squares next to free corners only if nothing else is left.
+	Guided
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MctsResult {
+	/// The move that has been tried most often, or None if the player cannot place a piece.
+	pub best: Option<(u8, u8)>,
+	/// The share of the playouts through the best move that the player has won, counting draws as
+	/// half a win.
+	pub win_rate: f64,
+	/// The number of games that have been played out on all threads.
+	pub playouts: u64
+}
+
+struct Node {
+	black: Bitboard,
+	white: Bitboard,
+	// The player to move, which is None once the game is over.
+	turn: Option<Piece>,
+	// The move that has led to this position.
+	square: (u8, u8),
+	children: Vec<usize>,
+	// The moves no child has been created for yet, as a bitboard.
+	untried: Bitboard,
+	visits: u32,
+	// The sum of the results of all playouts through this node, from the view of black.
+	black_wins: f64
+}
+
+impl Node {
+	/// The node of the position on the board covering `area`, in which it is the turn of the
+	/// player with the piece, unless they have to pass.
+	fn new(black: Bitboard, white: Bitboard, area: Bitboard, piece: Piece, square: (u8, u8)) -> Node {
+		let (own, opp) = match piece {
+			Piece::Black => (black, white),
+			Piece::White => (white, black)
+		};
+
+		let (turn, untried) = if legal_moves(own, opp, area) != 0 {
+			(Some(piece), legal_moves(own, opp, area))
+		}
+		else if legal_moves(opp, own, area) != 0 {
+			(Some(piece.opposite()), legal_moves(opp, own, area))
+		}
+		else { (None, 0) };
+
+		Node {
+			black: black,
+			white: white,
+			turn: turn,
+			square: square,
+			children: Vec::new(),
+			untried: untried,
+			visits: 0,
+			black_wins: 0.
+		}
+	}
+
+	/// The share of the playouts the player with the piece has won.
+	fn win_rate(&self, piece: Piece) -> f64 {
+		let black = self.black_wins / self.visits as f64;
+		match piece {
+			Piece::Black => black,
+			Piece::White => 1. - black
+		}
+	}
+}
+
+/// The position after the player with the piece has placed it on the square, as (black, white).
+fn play(black: Bitboard, white: Bitboard, piece: Piece, m: Bitboard) -> (Bitboard, Bitboard) {
+	match piece {
+		Piece::Black => {
+			let flips = flipped(m, black, white);
+			(black | flips | m, white & !flips)
+		},
+		Piece::White => {
+			let flips = flipped(m, white, black);
+			(black & !flips, white | flips | m)
+		}
+	}
+}
+
+/// Pick one of the squares of the bitboard at random.
+fn random_bit(bits: Bitboard, rng: &mut Rng) -> Bitboard {
+	let mut n = rng.below(bits.count_ones() as u64);
+	let mut bits = bits;
+	while n > 0 {
+		bits &= bits - 1;
+		n -= 1;
+	}
+
+	bits & bits.wrapping_neg()
+}
+
+/// One search tree, which is grown by a single thread.
+struct Tree {
+	nodes: Vec<Node>,
+	// The squares of the board and its corners with the squares next to them.
+	area: Bitboard,
+	corners: Bitboard,
+	x_squares: [(Bitboard, Bitboard); 4],
+	playout: Playout,
+	rng: Rng
+}
+
+impl Tree {
+	fn new(board: &Board, playout: Playout, rng: Rng) -> Tree {
+		let root = Node::new(board.pieces(Piece::Black), board.pieces(Piece::White), board.area(), board.turn(), (0, 0));
+		let x_squares = x_squares(board.size());
+
+		Tree {
+			nodes: vec![root],
+			area: board.area(),
+			corners: x_squares.iter().fold(0, |corners, &(corner, _)| { corners | corner }),
+			x_squares: x_squares,
+			playout: playout,
+			rng: rng
+		}
+	}
+
+	/// Select a leaf, add one of its moves to the tree, play the game out from there and let all
+	/// nodes on the way know about the result.
+	fn iterate(&mut self) {
+		let mut path = vec![0];
+		let mut current = 0;
+
+		while self.nodes[current].untried == 0 && !self.nodes[current].children.is_empty() {
+			current = self.select(current);
+			path.push(current);
+		}
+
+		if self.nodes[current].untried != 0 {
+			current = self.expand(current);
+			path.push(current);
+		}
+
+		let (black, white, turn) = {
+			let node = &self.nodes[current];
+			(node.black, node.white, node.turn)
+		};
+		let result = match turn {
+			Some(turn) => self.play_out(black, white, turn),
+			None => black_result(black, white)
+		};
+
+		for i in path {
+			self.nodes[i].visits += 1;
+			self.nodes[i].black_wins += result;
+		}
+	}
+
+	/// The child of the node with the highest upper confidence bound (UCT).
+	fn select(&self, parent: usize) -> usize {
+		let node = &self.nodes[parent];
+		let turn = node.turn.expect("Only nodes of running games have children.");
+		let log_visits = (node.visits as f64).ln();
+
+		let mut best = node.children[0];
+		let mut best_value = -1.;
+		for &child in &node.children {
+			let c = &self.nodes[child];
+			let value = c.win_rate(turn) + EXPLORATION * (log_visits / c.visits as f64).sqrt();
+			if value > best_value {
+				best = child;
+				best_value = value;
+			}
+		}
+
+		best
+	}
+
+	/// Add one of the untried moves of the node as a new child and return it.
+	fn expand(&mut self, parent: usize) -> usize {
+		let m = random_bit(self.nodes[parent].untried, &mut self.rng);
+		self.nodes[parent].untried &= !m;
+
+		let child = {
+			let node = &self.nodes[parent];
+			let turn = node.turn.expect("Only nodes of running games have untried moves.");
+			let (black, white) = play(node.black, node.white, turn, m);
+			Node::new(black, white, self.area, turn.opposite(), bit_square(m.trailing_zeros()))
+		};
+
+		self.nodes.push(child);
+		let index = self.nodes.len() - 1;
+		self.nodes[parent].children.push(index);
+		index
+	}
+
+	/// Play the game to the end and return the result for black.
+	fn play_out(&mut self, mut black: Bitboard, mut white: Bitboard, mut turn: Piece) -> f64 {
+		let mut passed = false;
+		loop {
+			let (own, opp) = match turn {
+				Piece::Black => (black, white),
+				Piece::White => (white, black)
+			};
+
+			let moves = legal_moves(own, opp, self.area);
+			if moves == 0 {
+				if passed {
+					return black_result(black, white);
+				}
+
+				passed = true;
+				turn = turn.opposite();
+				continue;
+			}
+
+			let m = self.choose_playout_move(moves, own | opp);
+			let (b, w) = play(black, white, turn, m);
+			black = b;
+			white = w;
+			passed = false;
+			turn = turn.opposite();
+		}
+	}
+
+	fn choose_playout_move(&mut self, moves: Bitboard, occupied: Bitboard) -> Bitboard {
+		if let Playout::Guided = self.playout {
+			if moves & self.corners != 0 {
+				return random_bit(moves & self.corners, &mut self.rng);
+			}
+
+			// Only avoid the squares next to corners that are still empty.
+			let mut dangerous = 0;
+			for &(corner, x_square) in &self.x_squares {
+				if occupied & corner == 0 {
+					dangerous |= x_square;
+				}
+			}
+
+			if moves & !dangerous != 0 {
+				return random_bit(moves & !dangerous, &mut self.rng);
+			}
+		}
+
+		random_bit(moves, &mut self.rng)
+	}
+
+	/// The visits and the results of all moves of the root, from the view of the player to move.
+	fn root_moves(&self) -> Vec<((u8, u8), u32, f64)> {
+		let root = &self.nodes[0];
+		let turn = match root.turn {
+			Some(turn) => turn,
+			None => return Vec::new()
+		};
+
+		root.children.iter().map(|&c| {
+			let child = &self.nodes[c];
+			(child.square, child.visits, child.win_rate(turn) * child.visits as f64)
+		}).collect()
+	}
+}
+
+/// The result of a finished game for black: 1 for a win, 0.5 for a draw and 0 for a loss.
+fn black_result(black: Bitboard, white: Bitboard) -> f64 {
+	if black.count_ones() > white.count_ones() {
+		1.
+	}
+	else if black.count_ones() < white.count_ones() {
+		0.
+	}
+	else { 0.5 }
+}
+
+/// Monte Carlo tree search, which runs an independent tree on every thread and combines the
+/// results of their first moves at the end.
+pub struct Mcts {
+	playouts: u64,
+	time_limit: Option<Duration>,
+	threads: usize,
+	playout: Playout,
+	rng: Rng
+}
+
+impl Mcts {
+	/// Create a search that plays out the given number of games for every move, split evenly on
+	/// all threads.
+	pub fn new(playouts: u64) -> Mcts {
+		Mcts {
+			playouts: playouts,
+			time_limit: None,
+			threads: 1,
+			playout: Playout::Guided,
+			rng: Rng::from_time()
+		}
+	}
+
+	/// Stop the search after the time, even if not all playouts have been made.
+	pub fn with_time_limit(mut self, time_limit: Duration) -> Mcts {
+		self.time_limit = Some(time_limit);
+		self
+	}
+
+	/// Grow the trees on the number of threads provided.
+	pub fn with_threads(mut self, threads: usize) -> Mcts {
+		assert!(threads > 0);
+		self.threads = threads;
+		self
+	}
+
+	pub fn with_playout(mut self, playout: Playout) -> Mcts {
+		self.playout = playout;
+		self
+	}
+
+	/// Search for the best move of the player whos turn it is.
+	pub fn search(&mut self, board: &Board) -> MctsResult {
+		match board.status() {
+			Status::InProgress { .. } => {},
+			_ => return MctsResult {
+				best: None,
+				win_rate: 0.,
+				playouts: 0
+			}
+		}
+
+		let deadline = self.time_limit.map(|t| { Instant::now() + t });
+		let per_thread = (self.playouts + self.threads as u64 - 1) / self.threads as u64;
+
+		let handles: Vec<_> = (0..self.threads).map(|_| {
+			let mut tree = Tree::new(board, self.playout, Rng::new(self.rng.next_u64()));
+			thread::spawn(move || {
+				let mut playouts = 0;
+				while playouts < per_thread {
+					// Looking at the clock is not free, so only do it every now and then.
+					if playouts % 64 == 0 {
+						if let Some(deadline) = deadline {
+							if Instant::now() >= deadline {
+								break;
+							}
+						}
+					}
+
+					tree.iterate();
+					playouts += 1;
+				}
+
+				(tree.root_moves(), playouts)
+			})
+		}).collect();
+
+		let mut moves: Vec<((u8, u8), u32, f64)> = Vec::new();
+		let mut playouts = 0;
+		for handle in handles {
+			let (root_moves, count) = handle.join().expect("A search thread has panicked.");
+			playouts += count;
+
+			for (square, visits, wins) in root_moves {
+				match moves.iter_mut().find(|m| { m.0 == square }) {
+					Some(m) => {
+						m.1 += visits;
+						m.2 += wins;
+					},
+					None => moves.push((square, visits, wins))
+				}
+			}
+		}
+
+		// The move that has been visited most often is more reliable than the one with the best
+		// win rate, which might have been tried only a few times.
+		match moves.iter().max_by_key(|m| { m.1 }) {
+			Some(&(square, visits, wins)) => MctsResult {
+				best: Some(square),
+				win_rate: wins / visits as f64,
+				playouts: playouts
+			},
+			// Not even one playout has been made, so take any move.
+			None => MctsResult {
+				best: board.opportunities(board.turn()).first().cloned(),
+				win_rate: 0.5,
+				playouts: playouts
+			}
+		}
+	}
+}
+
+impl Engine for Mcts {
+	fn choose_move(&mut self, board: &Board) -> Option<(u8, u8)> {
+		self.search(board).best
+	}
+}
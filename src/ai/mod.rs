@@ -0,0 +1,27 @@
+//! Computer players, which decide on their own where to place their pieces.
+pub mod book;
+pub use self::book::*;
+
+pub mod endgame;
+pub use self::endgame::*;
+
+pub mod eval;
+pub use self::eval::*;
+
+pub mod mcts;
+pub use self::mcts::*;
+
+pub mod search;
+pub use self::search::*;
+
+pub mod table;
+pub use self::table::*;
+
+use board::Board;
+
+/// Anything that can choose a move for the player whos turn it is.
+pub trait Engine: Send {
+	/// Returns the position the piece should be placed at, or None in case the player cannot
+	/// place a piece anywhere.
+	fn choose_move(&mut self, board: &Board) -> Option<(u8, u8)>;
+}
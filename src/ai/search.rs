@@ -0,0 +1,293 @@
+use std::i32;
+use board::{Bitboard, Board, Move, Piece, Status, bit_square};
+use ai::{Bound, EndgameSolver, Engine, Entry, Evaluator, TranspositionTable, SQUARE_WEIGHTS, square_weight};
+
+/// The score of a game that has been won. The difference in pieces is added to it, so that higher
+/// wins are preferred.
+pub const WIN_SCORE: i32 = 1000000;
+const INFINITY: i32 = i32::MAX;
+
+/// The number of positions the transposition table of a search holds by default.
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+/// How far the search is allowed to go.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+	/// Search the given number of moves ahead.
+	Depth(u8),
+	/// Search as deep as possible, without visiting more positions than the given number. The
+	/// first move ahead is always searched completely.
+	Nodes(u64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchResult {
+	/// The best move that has been found, or None if the player cannot place a piece.
+	pub best: Option<(u8, u8)>,
+	/// The score of the best move from the view of the player whos turn it is.
+	pub score: i32,
+	/// The number of moves that have been searched ahead completely.
+	pub depth: u8,
+	/// The number of positions that have been visited.
+	pub nodes: u64
+}
+
+/// The score of a finished game from the view of the player whos turn it is.
+pub fn final_score(board: &Board) -> i32 {
+	let turn = board.turn();
+	result_score(board.count(turn) as i32 - board.count(turn.opposite()) as i32)
+}
+
+/// The score of a game that ends with the difference in pieces provided.
+pub fn result_score(diff: i32) -> i32 {
+	if diff > 0 {
+		WIN_SCORE + diff
+	}
+	else if diff < 0 {
+		-WIN_SCORE + diff
+	}
+	else { 0 }
+}
+
+/// Take back the last move, including the pass that might have been made automatically after it.
+pub fn take_back(board: &mut Board) {
+	if let Some(Move::Pass(_)) = board.undo() {
+		board.undo();
+	}
+}
+
+/// Sort the moves so that the most promising ones are searched first, which lets alpha-beta cut
+/// off much more of the tree. The move provided as first is always tried first.
+fn ordered_moves(mut moves: Bitboard, size: u8, first: Option<(u8, u8)>) -> Vec<(u8, u8)> {
+	let mut ordered: Vec<(u8, u8)> = Vec::with_capacity(moves.count_ones() as usize);
+	while moves != 0 {
+		ordered.push(bit_square(moves.trailing_zeros()));
+		moves &= moves - 1;
+	}
+
+	ordered.sort_by_key(|&(x, y)| {
+		if Some((x, y)) == first { i32::MIN } else { -square_weight(&SQUARE_WEIGHTS, size, (x, y)) }
+	});
+	ordered
+}
+
+/// Negamax search with alpha-beta pruning. The positions at the end of the search are judged by
+/// the evaluator.
+pub struct AlphaBeta<E: Evaluator> {
+	evaluator: E,
+	limit: Limit,
+	// The depth of the current iteration of the search.
+	depth: u8,
+	nodes: u64,
+	// Set when the node budget has run out during the current iteration.
+	aborted: bool,
+	endgame: Option<EndgameSolver>,
+	table: TranspositionTable
+}
+
+impl<E: Evaluator> AlphaBeta<E> {
+	pub fn new(evaluator: E, limit: Limit) -> AlphaBeta<E> {
+		AlphaBeta {
+			evaluator: evaluator,
+			limit: limit,
+			depth: 0,
+			nodes: 0,
+			aborted: false,
+			endgame: None,
+			table: TranspositionTable::new(DEFAULT_TABLE_SIZE)
+		}
+	}
+
+	/// Use a transposition table with the number of slots provided.
+	pub fn with_table_size(mut self, size: usize) -> AlphaBeta<E> {
+		self.table = TranspositionTable::new(size);
+		self
+	}
+
+	/// Let the endgame solver play perfectly instead of searching, once there are few enough
+	/// empty squares left for it.
+	pub fn with_endgame(mut self, solver: EndgameSolver) -> AlphaBeta<E> {
+		self.endgame = Some(solver);
+		self
+	}
+
+	/// Search for the best move of the player whos turn it is. The search is deepened one move at
+	/// a time, until the limit is reached or the game has been searched to the end.
+	pub fn search(&mut self, board: &Board) -> SearchResult {
+		let mut board = board.clone();
+		self.nodes = 0;
+		self.table.new_search();
+
+		let mut result = SearchResult {
+			best: None,
+			score: self.evaluator.evaluate(&board),
+			depth: 0,
+			nodes: 0
+		};
+
+		let turn = match board.status() {
+			Status::InProgress { to_move } => to_move,
+			_ => return result
+		};
+
+		let empty = board.empty_count();
+		if let Some(ref mut solver) = self.endgame {
+			if let Some(solution) = solver.solve(&board) {
+				return SearchResult {
+					best: solution.best,
+					score: result_score(solution.diff),
+					depth: empty,
+					nodes: solution.nodes
+				};
+			}
+		}
+
+		let max_depth = match self.limit {
+			Limit::Depth(depth) => depth,
+			Limit::Nodes(_) => empty
+		};
+
+		for depth in 1..max_depth + 1 {
+			self.depth = depth;
+			self.aborted = false;
+
+			let (best, score) = match self.root(&mut board, turn, result.best) {
+				Some(bs) => bs,
+				None => break
+			};
+
+			result.best = Some(best);
+			result.score = score;
+			result.depth = depth;
+
+			// When the whole game has been searched, searching any deeper changes nothing.
+			if depth >= empty {
+				break;
+			}
+		}
+
+		result.nodes = self.nodes;
+		result
+	}
+
+	/// Search all moves of the player to move. Returns the best one with its score, or None if the
+	/// search has been aborted.
+	fn root(&mut self, board: &mut Board, turn: Piece, first: Option<(u8, u8)>) -> Option<((u8, u8), i32)> {
+		let mut best = None;
+		let mut alpha = -INFINITY;
+
+		for m in ordered_moves(board.legal_moves(turn), board.size(), first) {
+			board.place(m, turn).expect("Only legal moves are searched.");
+			let score = self.child_score(board, turn, self.depth - 1, alpha, INFINITY);
+			take_back(board);
+
+			if self.aborted {
+				return None;
+			}
+
+			if best.is_none() || score > alpha {
+				best = Some(m);
+				alpha = score;
+			}
+		}
+
+		best.map(|m| { (m, alpha) })
+	}
+
+	/// The score of the position after the player with the piece has moved, from their view.
+	fn child_score(&mut self, board: &mut Board, piece: Piece, depth: u8, alpha: i32, beta: i32) -> i32 {
+		// If the opponent had to pass, it is the players turn again.
+		if board.turn() == piece {
+			self.negamax(board, depth, alpha, beta)
+		}
+		else {
+			-self.negamax(board, depth, -beta, -alpha)
+		}
+	}
+
+	fn negamax(&mut self, board: &mut Board, depth: u8, mut alpha: i32, mut beta: i32) -> i32 {
+		self.nodes += 1;
+		if let Limit::Nodes(budget) = self.limit {
+			if self.depth > 1 && self.nodes >= budget {
+				self.aborted = true;
+				return 0;
+			}
+		}
+
+		let turn = match board.status() {
+			Status::InProgress { to_move } => to_move,
+			Status::MustPass => {
+				board.pass();
+				let score = -self.negamax(board, depth, -beta, -alpha);
+				board.undo();
+				return score;
+			},
+			Status::Finished { .. } => return final_score(board)
+		};
+
+		if depth == 0 {
+			return self.evaluator.evaluate(board);
+		}
+
+		// The position might have been searched before, in which case the score may already be
+		// good enough, or at least the best move is known.
+		let key = board.key();
+		let original_alpha = alpha;
+		let mut first = None;
+		if let Some(entry) = self.table.get(key) {
+			if entry.depth >= depth {
+				match entry.bound {
+					Bound::Exact => return entry.score,
+					Bound::Lower => alpha = alpha.max(entry.score),
+					Bound::Upper => beta = beta.min(entry.score)
+				}
+
+				if alpha >= beta {
+					return entry.score;
+				}
+			}
+
+			first = entry.best;
+		}
+
+		let mut best = -INFINITY;
+		let mut best_move = None;
+		for m in ordered_moves(board.legal_moves(turn), board.size(), first) {
+			board.place(m, turn).expect("Only legal moves are searched.");
+			let score = self.child_score(board, turn, depth - 1, alpha, beta);
+			take_back(board);
+
+			if self.aborted {
+				return 0;
+			}
+
+			if score > best {
+				best = score;
+				best_move = Some(m);
+			}
+			if score > alpha {
+				alpha = score;
+			}
+			if alpha >= beta {
+				break;
+			}
+		}
+
+		let bound = if best <= original_alpha {
+			Bound::Upper
+		}
+		else if best >= beta {
+			Bound::Lower
+		}
+		else { Bound::Exact };
+		self.table.store(Entry::new(key, depth, best, bound, best_move));
+
+		best
+	}
+}
+
+impl<E: Evaluator> Engine for AlphaBeta<E> {
+	fn choose_move(&mut self, board: &Board) -> Option<(u8, u8)> {
+		self.search(board).best
+	}
+}
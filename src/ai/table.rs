@@ -0,0 +1,105 @@
+//! A cache of positions that have already been searched, keyed by their Zobrist key. The same
+//! position can often be reached by several orders of moves, and with iterative deepening the
+//! positions of the last iteration are searched again, so this saves a lot of work.
+
+/// What the score of an entry says about the real score of the position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+	/// The score is exact.
+	Exact,
+	/// The real score is at least the one stored, since the search has been cut off.
+	Lower,
+	/// The real score is at most the one stored, since no move has reached alpha.
+	Upper
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+	/// The Zobrist key of the position.
+	pub key: u64,
+	/// The number of moves the position has been searched ahead.
+	pub depth: u8,
+	pub score: i32,
+	pub bound: Bound,
+	/// The best move that has been found, which should be tried first next time.
+	pub best: Option<(u8, u8)>,
+	// The search the entry has been stored in.
+	generation: u8
+}
+
+impl Entry {
+	pub fn new(key: u64, depth: u8, score: i32, bound: Bound, best: Option<(u8, u8)>) -> Entry {
+		Entry {
+			key: key,
+			depth: depth,
+			score: score,
+			bound: bound,
+			best: best,
+			generation: 0
+		}
+	}
+}
+
+/// A hash table with a fixed number of slots. When two positions fall into the same slot, the one
+/// from the current search that has been searched deeper is kept, while entries left over from
+/// earlier searches are always replaced.
+pub struct TranspositionTable {
+	slots: Vec<Option<Entry>>,
+	generation: u8
+}
+
+impl TranspositionTable {
+	/// Create a table with at least the number of slots provided. The number is rounded up to the
+	/// next power of two.
+	pub fn new(size: usize) -> TranspositionTable {
+		TranspositionTable {
+			slots: vec![None; size.next_power_of_two()],
+			generation: 0
+		}
+	}
+
+	fn index(&self, key: u64) -> usize {
+		(key as usize) & (self.slots.len() - 1)
+	}
+
+	/// The entry of the position with the key, if it is in the table.
+	pub fn get(&self, key: u64) -> Option<Entry> {
+		match self.slots[self.index(key)] {
+			Some(entry) if entry.key == key => Some(entry),
+			_ => None
+		}
+	}
+
+	/// Put the entry into the table, unless its slot holds a more valuable one.
+	pub fn store(&mut self, mut entry: Entry) {
+		entry.generation = self.generation;
+		let index = self.index(entry.key);
+
+		let replace = match self.slots[index] {
+			Some(old) => old.key == entry.key || old.generation != self.generation || old.depth <= entry.depth,
+			None => true
+		};
+
+		if replace {
+			self.slots[index] = Some(entry);
+		}
+	}
+
+	/// Let the table know that a new search is starting, so the entries of older searches are
+	/// replaced first.
+	pub fn new_search(&mut self) {
+		self.generation = self.generation.wrapping_add(1);
+	}
+
+	/// Remove all entries.
+	pub fn clear(&mut self) {
+		for slot in self.slots.iter_mut() {
+			*slot = None;
+		}
+	}
+
+	/// The number of slots of the table.
+	pub fn size(&self) -> usize {
+		self.slots.len()
+	}
+}
@@ -0,0 +1,165 @@
+//! Describes a position beyond the number of pieces, with the measures Othello players use to
+//! judge it: how many moves both players have, how exposed their pieces are, which pieces can never
+//! be flipped again and how the empty squares are split into regions.
+use board::{Bitboard, Board, DIRECTIONS, MAX_SIZE, Piece, legal_moves, shift};
+
+/// The four lines through a square, each as one of its two directions. The other direction is
+/// the opposite one.
+const AXES: [(i8, i8); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// All squares next to at least one of the squares of the bitboard.
+pub fn neighbours(bits: Bitboard) -> Bitboard {
+	DIRECTIONS.iter().fold(0, |n, &dir| { n | shift(bits, dir) })
+}
+
+/// The pieces of the player owning `own` that are next to an empty square of the board covering
+/// `area`. Those are the pieces the opponent can place next to, so having few of them is usually
+/// good.
+pub fn frontier(own: Bitboard, opp: Bitboard, area: Bitboard) -> Bitboard {
+	own & neighbours(area & !(own | opp))
+}
+
+/// The empty squares next to the pieces of the opponent. The player may be able to place a
+/// piece there later, even if they cannot do so now.
+pub fn potential_mobility(own: Bitboard, opp: Bitboard, area: Bitboard) -> Bitboard {
+	area & !(own | opp) & neighbours(opp)
+}
+
+/// The occupied squares from which every square in the direction up to the edge of the board is
+/// occupied as well.
+fn filled_towards(occupied: Bitboard, area: Bitboard, dir: (i8, i8)) -> Bitboard {
+	let back = (-dir.0, -dir.1);
+	// The squares that do not have a neighbour in the direction.
+	let edge = area & !shift(area, back);
+
+	let mut filled = 0;
+	for _ in 0..MAX_SIZE {
+		filled = occupied & (edge | shift(filled, back));
+	}
+
+	filled
+}
+
+/// The pieces of the player owning `own` that can never be flipped again, no matter what either
+/// player does. A piece is stable if on each of the four lines through it, the line is completely
+/// filled, or it is next to the edge or a stable own piece in at least one direction. This misses
+/// a few pieces that are stable for more subtle reasons, but never reports one that is not.
+pub fn stable_pieces(own: Bitboard, opp: Bitboard, area: Bitboard) -> Bitboard {
+	let occupied = own | opp;
+	let mut safe_lines = [0; 4];
+	let mut edges = [(0, 0); 4];
+	for (i, &dir) in AXES.iter().enumerate() {
+		let back = (-dir.0, -dir.1);
+		safe_lines[i] = filled_towards(occupied, area, dir) & filled_towards(occupied, area, back);
+		edges[i] = (area & !shift(area, back), area & !shift(area, dir));
+	}
+
+	let mut stable = 0;
+	loop {
+		let mut next = own;
+		for (i, &dir) in AXES.iter().enumerate() {
+			let back = (-dir.0, -dir.1);
+			let (edge, back_edge) = edges[i];
+			next &= safe_lines[i] | edge | back_edge | shift(stable, back) | shift(stable, dir);
+		}
+
+		if next == stable {
+			return stable;
+		}
+
+		stable = next;
+	}
+}
+
+/// Split the empty squares into regions of squares that are connected horizontally, vertically or
+/// diagonally.
+pub fn empty_regions(mut empty: Bitboard) -> Vec<Bitboard> {
+	let mut regions = Vec::new();
+	while empty != 0 {
+		let mut region = empty & empty.wrapping_neg();
+		loop {
+			let grown = (region | neighbours(region)) & empty;
+			if grown == region {
+				break;
+			}
+
+			region = grown;
+		}
+
+		empty &= !region;
+		regions.push(region);
+	}
+
+	regions
+}
+
+/// The measures of the position for one of the players.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SideAnalysis {
+	/// The number of moves the player has.
+	pub mobility: u32,
+	/// The number of empty squares next to the pieces of the opponent.
+	pub potential_mobility: u32,
+	/// The number of own pieces next to an empty square.
+	pub frontier: u32,
+	/// The number of own pieces that can never be flipped.
+	pub stable: u32
+}
+
+impl SideAnalysis {
+	fn new(own: Bitboard, opp: Bitboard, area: Bitboard) -> SideAnalysis {
+		SideAnalysis {
+			mobility: legal_moves(own, opp, area).count_ones(),
+			potential_mobility: potential_mobility(own, opp, area).count_ones(),
+			frontier: frontier(own, opp, area).count_ones(),
+			stable: stable_pieces(own, opp, area).count_ones()
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+	pub black: SideAnalysis,
+	pub white: SideAnalysis,
+	/// The regions of empty squares as bitboards.
+	pub regions: Vec<Bitboard>
+}
+
+impl Analysis {
+	/// The measures of the player with the piece.
+	pub fn side(&self, piece: Piece) -> &SideAnalysis {
+		match piece {
+			Piece::Black => &self.black,
+			Piece::White => &self.white
+		}
+	}
+
+	/// The number of regions with an odd number of empty squares. Without passes, the player to
+	/// move gets the last move in each of them.
+	pub fn odd_regions(&self) -> usize {
+		self.regions.iter().filter(|r| { r.count_ones() % 2 == 1 }).count()
+	}
+}
+
+impl Board {
+	/// Measure the position for both players.
+	pub fn analyse(&self) -> Analysis {
+		let (black, white, area) = (self.pieces(Piece::Black), self.pieces(Piece::White), self.area());
+
+		Analysis {
+			black: SideAnalysis::new(black, white, area),
+			white: SideAnalysis::new(white, black, area),
+			regions: empty_regions(area & !(black | white))
+		}
+	}
+
+	/// The pieces of the colour provided that can never be flipped again.
+	pub fn stable_pieces(&self, piece: Piece) -> Bitboard {
+		stable_pieces(self.pieces(piece), self.pieces(piece.opposite()), self.area())
+	}
+
+	/// The pieces of the colour provided that are next to an empty square.
+	pub fn frontier(&self, piece: Piece) -> Bitboard {
+		frontier(self.pieces(piece), self.pieces(piece.opposite()), self.area())
+	}
+}
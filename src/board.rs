@@ -1,5 +1,12 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use score::GameResult;
+use square::Square;
+use transcript::{ReplayError, Transcript};
+
 // A piece that might be placed on the board.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Piece {
 	Black,
 	White
@@ -15,164 +22,711 @@ impl Piece {
 	}
 }
 
+/// The eight directions a line of pieces can run in, as (dx, dy).
+pub const DIRECTIONS: [(i8, i8); 8] = [(-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1)];
+
+/// A set of squares with one bit per square. Boards of every size use the same layout, with rows
+/// as long as the largest board, so a board only uses the bits in its upper left corner.
+pub type Bitboard = u128;
+
+/// The side length of the smallest and the largest board. Only even sizes are allowed, so the
+/// four pieces of the start position can sit right in the middle.
+pub const MIN_SIZE: u8 = 4;
+pub const MAX_SIZE: u8 = 10;
+/// The size of the standard board.
+pub const DEFAULT_SIZE: u8 = 8;
+
+// The number of bits per row.
+const STRIDE: u32 = MAX_SIZE as u32;
+
+// Masks to prevent pieces from wrapping around to the other side of the board when shifting
+// to the left or the right.
+const NOT_LEFT_COLUMN: Bitboard = 0xfeffbfeffbfeffbfeffbfeffbfeffbfe;
+const NOT_RIGHT_COLUMN: Bitboard = 0xff7fdff7fdff7fdff7fdff7fdff7fdff;
+
+/// Check if a board with the side length can be created.
+pub fn valid_size(size: u8) -> bool {
+	size >= MIN_SIZE && size <= MAX_SIZE && size % 2 == 0
+}
+
+/// All squares of a board with the side length provided.
+pub fn board_mask(size: u8) -> Bitboard {
+	let row: Bitboard = (1 << size) - 1;
+	(0..size as u32).fold(0, |mask, y| { mask | row << (y * STRIDE) })
+}
+
+/// Returns the bit of the square at the position. The squares are counted row by row, starting
+/// in the upper left corner.
+pub fn square_bit((x, y): (u8, u8)) -> Bitboard {
+	1 << (y as u32 * STRIDE + x as u32)
+}
+
+/// Converts the index of a bit back to the position of the square it represents.
+pub fn bit_square(index: u32) -> (u8, u8) {
+	((index % STRIDE) as u8, (index / STRIDE) as u8)
+}
+
+/// Move every square of the bitboard one step into the direction. Squares that would leave the
+/// largest board are dropped, those that leave a smaller one have to be masked out with its area.
+pub fn shift(bits: Bitboard, (dx, dy): (i8, i8)) -> Bitboard {
+	let amount = dy as i32 * STRIDE as i32 + dx as i32;
+	let shifted = if amount > 0 { bits << amount } else { bits >> -amount };
+
+	match dx {
+		1 => shifted & NOT_LEFT_COLUMN,
+		-1 => shifted & NOT_RIGHT_COLUMN,
+		_ => shifted
+	}
+}
+
+/// Calculate all squares the player owning `own` could place a piece on, on the board covering
+/// `area`.
+pub fn legal_moves(own: Bitboard, opp: Bitboard, area: Bitboard) -> Bitboard {
+	let empty = area & !(own | opp);
+	let mut moves = 0;
+
+	for &dir in DIRECTIONS.iter() {
+		// Collect the runs of opponent pieces that are adjacent to one of the own pieces. A run
+		// can be two pieces shorter than the largest board at the most.
+		let mut run = shift(own, dir) & opp;
+		for _ in 0..MAX_SIZE - 3 {
+			run |= shift(run, dir) & opp;
+		}
+
+		moves |= shift(run, dir) & empty;
+	}
+
+	moves
+}
+
+/// The pieces that would be flipped in the direction, if a piece were placed on `square`.
+fn flips_in_direction(square: Bitboard, own: Bitboard, opp: Bitboard, dir: (i8, i8)) -> Bitboard {
+	let mut flips = 0;
+	let mut cur = shift(square, dir);
+	while cur & opp != 0 {
+		flips |= cur;
+		cur = shift(cur, dir);
+	}
+
+	// The line only counts if it has been closed off by an own piece.
+	if cur & own != 0 { flips } else { 0 }
+}
+
+/// All pieces of the opponent that would be flipped, if the player owning `own` placed a piece on
+/// `square`.
+pub fn flipped(square: Bitboard, own: Bitboard, opp: Bitboard) -> Bitboard {
+	DIRECTIONS.iter().fold(0, |flips, &dir| {
+		flips | flips_in_direction(square, own, opp, dir)
+	})
+}
+
+// Part of the Zobrist key whenever it is white's turn.
+const ZOBRIST_WHITE_TO_MOVE: u64 = 0x4b1f3e7a5c2d9081;
+
+/// The random number for the piece on the square with the index, which is xored into the Zobrist
+/// key of every position with that piece there. The numbers are derived from the square and
+/// colour alone (splitmix64), so the keys are the same on every machine.
+pub fn zobrist_square(piece: Piece, index: u32) -> u64 {
+	let colour = match piece {
+		Piece::Black => 0,
+		Piece::White => 1
+	};
+
+	let mut z = (index as u64 * 2 + colour + 1).wrapping_mul(0x9e3779b97f4a7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+	z ^ (z >> 31)
+}
+
+/// The combined Zobrist numbers of the piece on all squares of the bitboard.
+pub fn zobrist_bits(piece: Piece, mut bits: Bitboard) -> u64 {
+	let mut key = 0;
+	while bits != 0 {
+		key ^= zobrist_square(piece, bits.trailing_zeros());
+		bits &= bits - 1;
+	}
+
+	key
+}
+
+/// The Zobrist key of the position. Equal positions always have the same key, and different
+/// ones almost never do.
+pub fn zobrist(black: Bitboard, white: Bitboard, turn: Piece) -> u64 {
+	let key = zobrist_bits(Piece::Black, black) ^ zobrist_bits(Piece::White, white);
+	match turn {
+		Piece::Black => key,
+		Piece::White => key ^ ZOBRIST_WHITE_TO_MOVE
+	}
+}
+
+/// Count the positions that can be reached from the one of the player owning `own` in exactly
+/// `depth` moves. A pass counts as a move, while a finished game counts as a single position,
+/// however many moves were left.
+fn perft(own: Bitboard, opp: Bitboard, area: Bitboard, depth: u8, passed: bool) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
+
+	let mut moves = legal_moves(own, opp, area);
+	if moves == 0 {
+		// When both players have had to pass, the game is over.
+		return if passed { 1 } else { perft(opp, own, area, depth - 1, true) };
+	}
+
+	if depth == 1 {
+		return moves.count_ones() as u64;
+	}
+
+	let mut count = 0;
+	while moves != 0 {
+		let m = moves & moves.wrapping_neg();
+		moves &= moves - 1;
+
+		let flips = flipped(m, own, opp);
+		count += perft(opp & !flips, own | flips | m, area, depth - 1, false);
+	}
+
+	count
+}
+
+/// A single move that has been made on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+	/// The piece has been placed on the square and has flipped all pieces of the bitboard.
+	Place { piece: Piece, square: (u8, u8), flips: Bitboard },
+	/// The player with the piece has passed.
+	Pass(Piece)
+}
+
+impl Move {
+	/// The colour of the player that has made the move.
+	pub fn piece(&self) -> Piece {
+		match self {
+			&Move::Place { piece, .. } => piece,
+			&Move::Pass(piece) => piece
+		}
+	}
+}
+
+/// The state of the game that is being played on a board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+	/// The game is running and the player to move has at least one piece to place.
+	InProgress { to_move: Piece },
+	/// The player whos turn it is cannot place a piece anywhere and has to pass.
+	MustPass,
+	/// Neither player can place a piece any more. The result is decided by the pieces on the board,
+	/// with the most pieces winning. See Score for misère games and other ways to count them.
+	Finished { result: GameResult }
+}
+
+/// The reasons a piece may not be placed on a square.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaceError {
+	/// It is the other players turn.
+	WrongTurn,
+	/// The square is not on the board.
+	OffBoard,
+	/// There already is a piece on the square.
+	Occupied,
+	/// The square is blocked, so no piece can ever be placed on it.
+	Blocked,
+	/// The piece would not flip any pieces of the opponent.
+	NoFlips
+}
+
+/// Everything that has changed on the board when a piece has been placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveOutcome {
+	pub piece: Piece,
+	pub square: (u8, u8),
+	/// The bitboard of all pieces that have been flipped.
+	pub flips: Bitboard,
+	/// Set when the opponent could not place a piece afterwards and has passed automatically.
+	pub opponent_passed: bool
+}
+
+impl MoveOutcome {
+	/// The squares of all pieces that have been flipped.
+	pub fn flipped(&self) -> Vec<(u8, u8)> {
+		let mut flips = self.flips;
+		let mut squares = Vec::with_capacity(flips.count_ones() as usize);
+		while flips != 0 {
+			squares.push(bit_square(flips.trailing_zeros()));
+			flips &= flips - 1;
+		}
+
+		squares
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PositionError {
+	/// The position needs the squares of a board of a valid size and the piece to move, but had
+	/// the number of characters provided.
+	WrongLength(usize),
+	/// The character of the square with the index is neither a piece, empty nor blocked.
+	InvalidSquare(usize, char),
+	/// The character for the player to move is not a piece.
+	InvalidTurn(char)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Board {
-	// Contains the info about the 8x8 board.
-	squares: Vec<Vec<Option<Piece>>>,
-	turn: Piece
+	// The side length of the board, the squares that are blocked and the bitboard of all other
+	// squares, on which the game is played.
+	size: u8,
+	blocked: Bitboard,
+	area: Bitboard,
+	// The board is stored as one bitboard per colour.
+	black: Bitboard,
+	white: Bitboard,
+	turn: Piece,
+	// The Zobrist key of the position, which is updated with every move.
+	key: u64,
+	// All moves that have been made, the latest one last.
+	history: Vec<Move>,
+	// Moves that have been undone and can be redone, the one to redo next last.
+	undone: Vec<Move>
 }
 
 impl Board {
-	/// Create a new board that
+	/// Create a new 8x8 board with the standard starting position.
 	pub fn new() -> Board {
-		// Program in the starting information.
-		let mut squares: Vec<Vec<Option<Piece>>> = vec![vec![None; 8]; 8];
-		squares[3][3] = Some(Piece::White);
-		squares[4][4] = Some(Piece::White);
-		squares[3][4] = Some(Piece::Black);
-		squares[4][3] = Some(Piece::Black);
+		Board::with_size(DEFAULT_SIZE)
+	}
 
+	/// Create a new board with the side length provided, with the four starting pieces in the
+	/// centre. Panics if the size is not valid.
+	pub fn with_size(size: u8) -> Board {
+		assert!(valid_size(size), "Invalid board size {}", size);
+
+		let c = size / 2;
+		let black = square_bit((c - 1, c)) | square_bit((c, c - 1));
+		let white = square_bit((c - 1, c - 1)) | square_bit((c, c));
+
+		Board {
+			size: size,
+			blocked: 0,
+			area: board_mask(size),
+			black: black,
+			white: white,
+			turn: Piece::Black,
+			key: zobrist(black, white, Piece::Black),
+			history: Vec::new(),
+			undone: Vec::new()
+		}
+	}
+
+	/// Create a board with the pieces of both colours given as bitboards and no history. The
+	/// position is not checked, so it does not have to be reachable in a real game. Pieces outside
+	/// of the board are dropped. Panics if the size is not valid.
+	pub fn from_pieces(size: u8, black: Bitboard, white: Bitboard, turn: Piece) -> Board {
+		assert!(valid_size(size), "Invalid board size {}", size);
+
+		let area = board_mask(size);
+		let black = black & area;
+		let white = white & area & !black;
 		Board {
-			squares: squares,
-			turn: Piece::Black
+			size: size,
+			blocked: 0,
+			area: area,
+			black: black,
+			white: white,
+			turn: turn,
+			key: zobrist(black, white, turn),
+			history: Vec::new(),
+			undone: Vec::new()
 		}
 	}
 
+	/// Block the squares of the bitboard, so no piece can be placed on them and no line of pieces
+	/// can run through them. Pieces on those squares are removed.
+	pub fn with_blocked(mut self, blocked: Bitboard) -> Board {
+		self.blocked = blocked & board_mask(self.size);
+		self.area = board_mask(self.size) & !self.blocked;
+		self.black &= self.area;
+		self.white &= self.area;
+		self.key = zobrist(self.black, self.white, self.turn);
+		self
+	}
+
 	/// Gets all opportunities for the provided piece. It is irrellevant whos turn it is, the board
 	/// will check everything as if it were the turn of the provided piece.
 	pub fn opportunities(&self, piece: Piece) -> Vec<(u8, u8)> {
-		// First, create a vector of all places where there is no piece. This will speed up the
-		// process a little bit, since can_place() is a fairly costly operation.
-		let mut opportunities: Vec<(u8, u8)> = Vec::new();
-		for x in 0..8 {
-			for y in 0..8 {
-				if self.squares[x][y].is_none() {
-					opportunities.push((x as u8, y as u8));
-				}
-			}
+		let mut moves = self.legal_moves(piece);
+		let mut opportunities: Vec<(u8, u8)> = Vec::with_capacity(moves.count_ones() as usize);
+		while moves != 0 {
+			opportunities.push(bit_square(moves.trailing_zeros()));
+			moves &= moves - 1;
 		}
 
-		opportunities.retain(|&(ref x, ref y)| { self.can_place((*x, *y), piece) });
 		opportunities
 	}
 
+	/// Gets all opportunities for the provided piece as a bitboard. This is the fast version of
+	/// opportunities(), which does not need to allocate.
+	pub fn legal_moves(&self, piece: Piece) -> Bitboard {
+		legal_moves(self.pieces(piece), self.pieces(piece.opposite()), self.area)
+	}
+
 	/// Check if a stone with the given colour can be placed at the point in
 	/// question.
 	pub fn can_place(&self, (x, y): (u8, u8), piece: Piece) -> bool {
-		!self.affected_directions((x, y), piece).is_empty()
+		self.on_board((x, y)) && self.legal_moves(piece) & square_bit((x, y)) != 0
 	}
 
 	/// Returns a vector of directions that would be affected, should the piece
 	/// be placed at the square in question.
 	pub fn affected_directions(&self, (x, y): (u8, u8), piece: Piece) -> Vec<(i8, i8)> {
-		let dirs: Vec<(i8, i8)> = vec![(-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1)];
-		let mut aff_dirs: Vec<(i8, i8)> = Vec::new();
-
-		for (dx, dy) in dirs {
-			let mut first = true;
-			let mut done = false;
-			let mut err_occured = false;
-
-			// Check the first stone next to the one in question.
-			let (mut cur_x, mut cur_y) = (x as i8, y as i8);
-			while !err_occured && !done {
-				cur_x += dx;
-				cur_y += dy;
-				if cur_x < 0 || cur_y < 0 || cur_x >= 8 || cur_y >= 8 {
-					err_occured = true;
-				}
-				else if Some(piece) == self.squares[cur_x as usize][cur_y as usize] {
-					// The current piece is the same as the one to check.
-					if first { err_occured = true; }
-					else { done = true; }
-				}
-				else if None == self.squares[cur_x as usize][cur_y as usize] {
-					// There is no further piece in the direction. The line has
-					// not been closed off properly.
-					err_occured = true;
-				}
+		if !self.on_board((x, y)) || self.is_blocked((x, y)) {
+			return Vec::new();
+		}
 
-				first = false;
-			}
+		let (own, opp) = (self.pieces(piece), self.pieces(piece.opposite()));
+		DIRECTIONS.iter().cloned().filter(|&dir| {
+			flips_in_direction(square_bit((x, y)), own, opp, dir) != 0
+		}).collect()
+	}
 
-			if first { err_occured = true }
-			if !err_occured && done {
-				aff_dirs.push((dx, dy));
-			}
+	/// Returns all the pieces that would be flipped, should the piece be placed at the square in
+	/// question.
+	pub fn flips(&self, (x, y): (u8, u8), piece: Piece) -> Bitboard {
+		if !self.on_board((x, y)) || self.is_blocked((x, y)) {
+			return 0;
 		}
 
-		aff_dirs
+		flipped(square_bit((x, y)), self.pieces(piece), self.pieces(piece.opposite()))
 	}
 
-	pub fn place(&mut self, (x, y): (u8, u8), piece: Piece) -> bool {
+	/// Place the piece on the square, if the rules allow it, and flip the pieces of the opponent.
+	/// Returns what has changed on the board, or the reason the piece may not be placed there.
+	pub fn place(&mut self, (x, y): (u8, u8), piece: Piece) -> Result<MoveOutcome, PlaceError> {
 		if self.turn != piece {
-			return false;
+			return Err(PlaceError::WrongTurn);
 		}
 
 		// Check if the given position is a valid position on the board.
-		if x >= 8 || y >= 8 {
-			return false;
+		if !self.on_board((x, y)) {
+			return Err(PlaceError::OffBoard);
+		}
+
+		if self.is_blocked((x, y)) {
+			return Err(PlaceError::Blocked);
 		}
 
 		// Cannot place a piece in case there is already one on the square.
-		if let Some(_) = self.squares[x as usize][y as usize] {
-			return false;
+		let bit = square_bit((x, y));
+		if (self.black | self.white) & bit != 0 {
+			return Err(PlaceError::Occupied);
 		}
 
-		let dirs = self.affected_directions((x, y), piece);
+		let flips = self.flips((x, y), piece);
+		if flips == 0 {
+			return Err(PlaceError::NoFlips);
+		}
+
+		// A new move has been made, so the moves that have been undone are lost.
+		self.undone.clear();
+		self.apply(Move::Place { piece: piece, square: (x, y), flips: flips });
 
-		if dirs.is_empty() {
+		// In case the next player cannot place any piece, they pass automatically.
+		let opponent_passed = self.status() == Status::MustPass;
+		if opponent_passed {
+			let turn = self.turn;
+			self.apply(Move::Pass(turn));
+		}
+
+		Ok(MoveOutcome {
+			piece: piece,
+			square: (x, y),
+			flips: flips,
+			opponent_passed: opponent_passed
+		})
+	}
+
+	pub fn turn(&self) -> Piece {
+		self.turn
+	}
+
+	/// The side length of the board.
+	pub fn size(&self) -> u8 {
+		self.size
+	}
+
+	/// The bitboard of all squares of the board that are not blocked.
+	pub fn area(&self) -> Bitboard {
+		self.area
+	}
+
+	/// The bitboard of all blocked squares.
+	pub fn blocked(&self) -> Bitboard {
+		self.blocked
+	}
+
+	/// Check if the square is blocked.
+	pub fn is_blocked(&self, (x, y): (u8, u8)) -> bool {
+		self.on_board((x, y)) && self.blocked & square_bit((x, y)) != 0
+	}
+
+	/// Check if the square is part of the board.
+	pub fn on_board(&self, (x, y): (u8, u8)) -> bool {
+		x < self.size && y < self.size
+	}
+
+	/// Pass the turn to the other player. A pass is only allowed when the player whos turn it is
+	/// cannot place a piece anywhere. Returns false if the pass has been refused.
+	pub fn pass(&mut self) -> bool {
+		if self.status() != Status::MustPass {
 			return false;
 		}
 
-		// Go through the directions and change the colour of every single piece
-		// that is the different colour until you reach the first of the same.
-		// If an error occurs here it is likely in affected_directions()
-		for (dx, dy) in dirs {
-			let (mut cur_x, mut cur_y) = (x as i8 + dx, y as i8 + dy);
-			assert!(cur_x >= 0 && cur_y >= 0 && cur_x < 8 && cur_y < 8);
+		self.undone.clear();
+		let turn = self.turn;
+		self.apply(Move::Pass(turn));
+		true
+	}
+
+	/// Check the state of the game, i.e. if it is still running, if the player to move must pass
+	/// or if the game is over.
+	pub fn status(&self) -> Status {
+		if self.legal_moves(self.turn) != 0 {
+			return Status::InProgress { to_move: self.turn };
+		}
 
-			// Flip all the pieces in the current direction.
-			while Some(piece.opposite()) == self.squares[cur_x as usize][cur_y as usize] {
-				self.squares[cur_x as usize][cur_y as usize] = Some(piece);
+		if self.legal_moves(self.turn.opposite()) != 0 {
+			return Status::MustPass;
+		}
 
-				cur_x += dx;
-				cur_y += dy;
-				// If this assertion fails, then the fault is in affected directions.
-				assert!(cur_x >= 0 && cur_y >= 0 && cur_x < 8 && cur_y < 8);
+		Status::Finished { result: GameResult::from_counts(self.count(Piece::Black), self.count(Piece::White)) }
+	}
+
+	/// Make the move on the board and add it to the history. The move is not checked, so it must
+	/// have been valid for the current board.
+	fn apply(&mut self, m: Move) {
+		if let Move::Place { piece, square, flips } = m {
+			// Change the colour of the flipped pieces and place the actual piece on the board.
+			let bit = square_bit(square);
+			match piece {
+				Piece::Black => {
+					self.black |= flips | bit;
+					self.white &= !flips;
+				},
+				Piece::White => {
+					self.white |= flips | bit;
+					self.black &= !flips;
+				}
 			}
+
+			self.key ^= zobrist_square(piece, bit.trailing_zeros()) ^ zobrist_bits(Piece::Black, flips) ^ zobrist_bits(Piece::White, flips);
 		}
 
-		// Place the actual piece on the board.
-		self.squares[x as usize][y as usize] = Some(piece);
+		// After a move it is always the other players turn.
+		self.turn = m.piece().opposite();
+		self.key ^= ZOBRIST_WHITE_TO_MOVE;
+		self.history.push(m);
+	}
 
-		// Since the piece could be set successfully, it is now the other players turn.
-		self.turn = self.turn.opposite();
+	/// Take back the last move or pass. Returns the move that has been undone, or None if there
+	/// was nothing to undo.
+	pub fn undo(&mut self) -> Option<Move> {
+		let m = match self.history.pop() {
+			Some(m) => m,
+			None => return None
+		};
 
-		true
+		if let Move::Place { piece, square, flips } = m {
+			// Remove the piece and give the flipped pieces back to the opponent.
+			let bit = square_bit(square);
+			match piece {
+				Piece::Black => {
+					self.black &= !(flips | bit);
+					self.white |= flips;
+				},
+				Piece::White => {
+					self.white &= !(flips | bit);
+					self.black |= flips;
+				}
+			}
+
+			self.key ^= zobrist_square(piece, bit.trailing_zeros()) ^ zobrist_bits(Piece::Black, flips) ^ zobrist_bits(Piece::White, flips);
+		}
+
+		self.turn = m.piece();
+		self.key ^= ZOBRIST_WHITE_TO_MOVE;
+		self.undone.push(m);
+		Some(m)
 	}
 
-	pub fn turn(&self) -> Piece {
-		self.turn
+	/// Make the last move that has been undone again. Returns the move, or None if there was
+	/// nothing to redo.
+	pub fn redo(&mut self) -> Option<Move> {
+		let m = match self.undone.pop() {
+			Some(m) => m,
+			None => return None
+		};
+
+		self.apply(m);
+		Some(m)
+	}
+
+	/// All moves and passes that have been made on this board, starting with the first one.
+	pub fn history(&self) -> &[Move] {
+		&self.history
+	}
+
+	/// The transcript of all moves and passes that have been made on this board.
+	pub fn transcript(&self) -> Transcript {
+		Transcript::new(self.history.iter().map(|m| {
+			match m {
+				&Move::Place { square, .. } => Some(Square::from(square)),
+				&Move::Pass(_) => None
+			}
+		}).collect())
+	}
+
+	/// Make all the moves of the transcript on this board. Since the board passes automatically,
+	/// the passes may be left out of the transcript. In case of an error, the moves up to the
+	/// faulty one stay on the board.
+	pub fn replay(&mut self, transcript: &Transcript) -> Result<(), ReplayError> {
+		// Set when the last move has made the board pass automatically, so the pass in the
+		// transcript has already been made.
+		let mut passed = false;
+		for (i, m) in transcript.moves().iter().enumerate() {
+			match m {
+				&Some(square) => {
+					let turn = self.turn;
+					passed = match self.place(square.pos(), turn) {
+						Ok(outcome) => outcome.opponent_passed,
+						Err(_) => return Err(ReplayError::IllegalMove(i, square))
+					};
+				},
+				&None => {
+					if !passed && !self.pass() {
+						return Err(ReplayError::IllegalPass(i));
+					}
+
+					passed = false;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The bitboard of all pieces of the colour provided.
+	pub fn pieces(&self, piece: Piece) -> Bitboard {
+		match piece {
+			Piece::Black => self.black,
+			Piece::White => self.white
+		}
 	}
 
-	pub fn pass(&mut self) {
-		self.turn = self.turn.opposite();
+	/// The number of pieces of the colour provided.
+	pub fn count(&self, piece: Piece) -> u8 {
+		self.pieces(piece).count_ones() as u8
 	}
 
-	pub fn squares(&self) -> &Vec<Vec<Option<Piece>>> {
-		&self.squares
+	/// The number of squares no piece has been placed on yet.
+	pub fn empty_count(&self) -> u8 {
+		(self.area & !(self.black | self.white)).count_ones() as u8
 	}
 
-	pub fn squares_mut(&mut self) -> &mut Vec<Vec<Option<Piece>>> {
-		&mut self.squares
+	/// Get the piece on the square, if there is any.
+	pub fn square(&self, (x, y): (u8, u8)) -> Option<Piece> {
+		if !self.on_board((x, y)) {
+			return None;
+		}
+
+		let bit = square_bit((x, y));
+		if self.black & bit != 0 {
+			Some(Piece::Black)
+		}
+		else if self.white & bit != 0 {
+			Some(Piece::White)
+		}
+		else { None }
+	}
+
+	/// Set the square to the piece provided, or clear it. This does not check any rules. Squares
+	/// that are not on the board or blocked are ignored. The moves made so far no longer lead to
+	/// the position, so they are forgotten and can neither be undone nor redone.
+	pub fn set_square(&mut self, (x, y): (u8, u8), piece: Option<Piece>) {
+		if !self.on_board((x, y)) || self.is_blocked((x, y)) {
+			return;
+		}
+
+		self.history.clear();
+		self.undone.clear();
+
+		let bit = square_bit((x, y));
+		self.black &= !bit;
+		self.white &= !bit;
+
+		match piece {
+			Some(Piece::Black) => self.black |= bit,
+			Some(Piece::White) => self.white |= bit,
+			None => {}
+		}
+
+		self.key = zobrist(self.black, self.white, self.turn);
+	}
+
+	/// The Zobrist key of the position, which identifies it regardless of the moves that have
+	/// led to it. It is kept up to date with every move, so getting it is free.
+	pub fn key(&self) -> u64 {
+		self.key
+	}
+
+	/// Copy of the board as columns of squares, where the square at (x, y) is at [x][y].
+	pub fn squares(&self) -> Vec<Vec<Option<Piece>>> {
+		(0..self.size).map(|x| {
+			(0..self.size).map(|y| { self.square((x, y)) }).collect()
+		}).collect()
+	}
+
+	/// Count all positions that can be reached from this one in exactly `depth` moves, which is
+	/// used to check that the moves are generated correctly. A pass counts as a move, while a
+	/// finished game counts as a single position.
+	pub fn perft(&self, depth: u8) -> u64 {
+		perft(self.pieces(self.turn), self.pieces(self.turn.opposite()), self.area, depth, false)
+	}
+
+	/// The perft count after each move of the player to move, or after their pass if they have to
+	/// pass, which is written as None. Unless the game is over, the counts add up to
+	/// perft(depth).
+	pub fn divide(&self, depth: u8) -> Vec<(Option<(u8, u8)>, u64)> {
+		let (own, opp) = (self.pieces(self.turn), self.pieces(self.turn.opposite()));
+		if depth == 0 {
+			return Vec::new();
+		}
+
+		match self.status() {
+			Status::InProgress { .. } => {},
+			Status::MustPass => return vec![(None, perft(opp, own, self.area, depth - 1, true))],
+			Status::Finished { .. } => return Vec::new()
+		}
+
+		let mut moves = legal_moves(own, opp, self.area);
+		let mut counts = Vec::with_capacity(moves.count_ones() as usize);
+		while moves != 0 {
+			let m = moves & moves.wrapping_neg();
+			moves &= moves - 1;
+
+			let flips = flipped(m, own, opp);
+			counts.push((Some(bit_square(m.trailing_zeros())), perft(opp & !flips, own | flips | m, self.area, depth - 1, false)));
+		}
+
+		counts
 	}
 
 	pub fn print(&self) {
-		for y in 0..8 {
-			for x in 0..8 {
-				match self.squares[x][y] {
+		for y in 0..self.size {
+			for x in 0..self.size {
+				match self.square((x, y)) {
 					Some(Piece::White) => print!("W"),
 					Some(Piece::Black) => print!("B"),
+					None if self.is_blocked((x, y)) => print!("#"),
 					None => print!("-")
 				};
 			}
@@ -180,3 +734,193 @@ impl Board {
 		}
 	}
 }
+
+/// Only the position is hashed, using its Zobrist key.
+impl Hash for Board {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.key.hash(state);
+	}
+}
+
+/// Writes the position as all squares row by row, followed by the piece to move, for example
+/// "---------------------------WB------BW--------------------------- B" for the start position.
+/// Blocked squares are written as #. The size of the board follows from the number of squares. The
+/// history of the board is not part of the position.
+impl fmt::Display for Board {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for y in 0..self.size {
+			for x in 0..self.size {
+				match self.square((x, y)) {
+					Some(Piece::White) => write!(f, "W")?,
+					Some(Piece::Black) => write!(f, "B")?,
+					None if self.is_blocked((x, y)) => write!(f, "#")?,
+					None => write!(f, "-")?
+				};
+			}
+		}
+
+		match self.turn {
+			Piece::White => write!(f, " W"),
+			Piece::Black => write!(f, " B")
+		}
+	}
+}
+
+/// Reads a position as it is written by Display. Whitespace is ignored, so the rows may be on
+/// separate lines. Since other programs often use X for black, O for white and . for empty
+/// squares, those are accepted as well.
+impl FromStr for Board {
+	type Err = PositionError;
+
+	fn from_str(s: &str) -> Result<Board, PositionError> {
+		let chars: Vec<char> = s.chars().filter(|c| { !c.is_whitespace() }).collect();
+		let size = match (MIN_SIZE..MAX_SIZE + 1).find(|&size| { size as usize * size as usize + 1 == chars.len() }) {
+			Some(size) if valid_size(size) => size,
+			_ => return Err(PositionError::WrongLength(chars.len()))
+		};
+
+		let squares = chars.len() - 1;
+		let (mut black, mut white, mut blocked) = (0, 0, 0);
+		for (i, &c) in chars[..squares].iter().enumerate() {
+			let bit = square_bit(((i % size as usize) as u8, (i / size as usize) as u8));
+			match c.to_ascii_uppercase() {
+				'B' | 'X' => black |= bit,
+				'W' | 'O' => white |= bit,
+				'-' | '.' => {},
+				'#' => blocked |= bit,
+				_ => return Err(PositionError::InvalidSquare(i, c))
+			}
+		}
+
+		let turn = match chars[squares].to_ascii_uppercase() {
+			'B' | 'X' => Piece::Black,
+			'W' | 'O' => Piece::White,
+			c => return Err(PositionError::InvalidTurn(c))
+		};
+
+		Ok(Board::from_pieces(size, black, white, turn).with_blocked(blocked))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn position_round_trip() {
+		let start = Board::new();
+		assert_eq!(start.to_string(), "---------------------------WB------BW--------------------------- B");
+		assert_eq!(start.to_string().parse::<Board>().unwrap().to_string(), start.to_string());
+
+		// A smaller board with blocked squares, after black has moved.
+		let mut board = Board::with_size(6).with_blocked(square_bit((0, 0)) | square_bit((5, 2)));
+		board.place((2, 1), Piece::Black).unwrap();
+		let text = board.to_string();
+		assert_eq!(text, "#-----\n--B---\n--BB-#\n--BW--\n------\n------ W".replace("\n", ""));
+
+		let parsed: Board = text.parse().unwrap();
+		assert_eq!(parsed.to_string(), text);
+		assert_eq!(parsed.size(), 6);
+		assert_eq!(parsed.blocked(), board.blocked());
+		assert_eq!(parsed.pieces(Piece::Black), board.pieces(Piece::Black));
+		assert_eq!(parsed.pieces(Piece::White), board.pieces(Piece::White));
+		assert_eq!(parsed.turn(), Piece::White);
+		assert_eq!(parsed.key(), board.key());
+	}
+
+	#[test]
+	fn position_aliases() {
+		// X, O and . in either case, with the rows on separate lines.
+		let text = "........\n........\n........\n...ox...\n...XO...\n........\n........\n........\no";
+		let board: Board = text.parse().unwrap();
+		assert_eq!(board.to_string(), "---------------------------WB------BW--------------------------- W");
+		assert_eq!(board.pieces(Piece::Black), Board::new().pieces(Piece::Black));
+		assert_eq!(board.pieces(Piece::White), Board::new().pieces(Piece::White));
+		assert_eq!(board.turn(), Piece::White);
+	}
+
+	#[test]
+	fn malformed_positions() {
+		let start = Board::new().to_string();
+
+		assert_eq!("B".parse::<Board>(), Err(PositionError::WrongLength(1)));
+		assert_eq!(start[1..].parse::<Board>(), Err(PositionError::WrongLength(64)));
+		// 25 squares would be a board of side length 5, which is not allowed.
+		assert_eq!(format!("{} B", "-".repeat(25)).parse::<Board>(), Err(PositionError::WrongLength(26)));
+
+		let square = format!("-----Z{}", &start[6..]);
+		assert_eq!(square.parse::<Board>(), Err(PositionError::InvalidSquare(5, 'Z')));
+
+		let turn = format!("{}-", &start[..start.len() - 1]);
+		assert_eq!(turn.parse::<Board>(), Err(PositionError::InvalidTurn('-')));
+	}
+
+	#[test]
+	fn perft_from_start() {
+		let board = Board::new();
+		let counts: Vec<u64> = (1..9).map(|depth| { board.perft(depth) }).collect();
+		assert_eq!(counts, vec![4, 12, 56, 244, 1396, 8200, 55092, 390216]);
+	}
+
+	#[test]
+	fn perft_counts_passes() {
+		// Black cannot move and has to pass, which counts as the only move at depth 1.
+		let board: Board = "---------B------W-B-----WWBBB---W--BB-------B------------------- B".parse().unwrap();
+		assert_eq!(board.status(), Status::MustPass);
+		assert_eq!(board.divide(3), vec![(None, 8)]);
+
+		let counts: Vec<u64> = (1..7).map(|depth| { board.perft(depth) }).collect();
+		assert_eq!(counts, vec![1, 3, 8, 57, 358, 2976]);
+	}
+
+	/// The key of the position computed from scratch.
+	fn recomputed_key(board: &Board) -> u64 {
+		zobrist(board.pieces(Piece::Black), board.pieces(Piece::White), board.turn())
+	}
+
+	#[test]
+	fn zobrist_key_stays_in_sync() {
+		// Black has to pass after the eighth move.
+		let moves: Transcript = "c4c3e6b4a4a5b2a3".parse().unwrap();
+		let mut board = Board::new();
+		assert_eq!(board.key(), recomputed_key(&board));
+
+		for square in moves.moves() {
+			let turn = board.turn();
+			board.place(square.unwrap().pos(), turn).unwrap();
+			assert_eq!(board.key(), recomputed_key(&board));
+		}
+		assert_eq!(board.history().last(), Some(&Move::Pass(Piece::Black)));
+
+		// White moves on after the pass.
+		let square = board.opportunities(Piece::White)[0];
+		board.place(square, Piece::White).unwrap();
+		assert_eq!(board.key(), recomputed_key(&board));
+
+		// Undo everything, including the pass, and then redo it all.
+		while board.undo().is_some() {
+			assert_eq!(board.key(), recomputed_key(&board));
+		}
+		assert_eq!(board.key(), Board::new().key());
+
+		while board.redo().is_some() {
+			assert_eq!(board.key(), recomputed_key(&board));
+		}
+		assert_eq!(board.history().len(), 10);
+	}
+
+	#[test]
+	fn set_square_forgets_moves() {
+		let mut board = Board::new();
+		board.place((2, 3), Piece::Black).unwrap();
+		board.place((2, 2), Piece::White).unwrap();
+		board.undo();
+		board.set_square((0, 0), Some(Piece::White));
+
+		let position = board.to_string();
+		assert_eq!(board.undo(), None);
+		assert_eq!(board.redo(), None);
+		assert_eq!(board.to_string(), position);
+		assert_eq!(board.key(), recomputed_key(&board));
+	}
+}
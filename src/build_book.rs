@@ -0,0 +1,117 @@
+//! Builds an opening book from a file of finished games, one transcript per line. The moves of the
+//! player who has won a game count twice as much as those of the loser.
+//!
+//! Usage: build_book <games> <book> [depth]
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod ai;
+pub mod analysis;
+pub mod board;
+pub mod rng;
+pub mod score;
+pub mod square;
+pub mod symmetry;
+pub mod transcript;
+
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::process;
+
+use ai::Book;
+use board::{Board, Status};
+use transcript::Transcript;
+
+/// The number of moves of every game that are put into the book, if no depth is provided.
+const DEFAULT_DEPTH: usize = 16;
+
+fn main() {
+	let args: Vec<String> = env::args().collect();
+	if args.len() < 3 || args.len() > 4 {
+		println!("Usage: {} <games> <book> [depth]", args[0]);
+		process::exit(1);
+	}
+
+	let depth = match args.get(3) {
+		Some(d) => d.parse().unwrap_or_else(|_| {
+			println!("The depth must be a number.");
+			process::exit(1);
+		}),
+		None => DEFAULT_DEPTH
+	};
+
+	let games = File::open(&args[1]).unwrap_or_else(|err| {
+		println!("Could not open {}: {}", args[1], err);
+		process::exit(1);
+	});
+
+	let mut book = Book::new();
+	let (mut added, mut skipped) = (0, 0);
+	for (i, line) in BufReader::new(games).lines().enumerate() {
+		let line = line.unwrap_or_else(|err| {
+			println!("Could not read {}: {}", args[1], err);
+			process::exit(1);
+		});
+
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		if add_game(&mut book, line, depth) {
+			added += 1;
+		}
+		else {
+			println!("[WARNING] Skipping line {}, which is not a finished game.", i + 1);
+			skipped += 1;
+		}
+	}
+
+	let output = File::create(&args[2]).unwrap_or_else(|err| {
+		println!("Could not create {}: {}", args[2], err);
+		process::exit(1);
+	});
+
+	if let Err(err) = book.write(&mut BufWriter::new(output)) {
+		println!("Could not write {}: {}", args[2], err);
+		process::exit(1);
+	}
+
+	println!("Added {} games, skipped {}. The book contains {} positions.", added, skipped, book.len());
+}
+
+/// Add the first moves of the game in the transcript to the book. Returns false if the transcript
+/// is not a legal game that has been played to the end.
+fn add_game(book: &mut Book, line: &str, depth: usize) -> bool {
+	let transcript: Transcript = match line.parse() {
+		Ok(transcript) => transcript,
+		Err(_) => return false
+	};
+
+	let mut board = Board::new();
+	if board.replay(&transcript).is_err() {
+		return false;
+	}
+
+	let winner = match board.status() {
+		Status::Finished { result } => result.winner(),
+		_ => return false
+	};
+
+	// Go through the game again, this time adding the moves.
+	let mut board = Board::new();
+	for m in transcript.moves().iter().take(depth) {
+		let square = match m {
+			&Some(square) => square,
+			&None => continue
+		};
+
+		let turn = board.turn();
+		book.add(&board, square.pos(), if winner == Some(turn) { 2 } else { 1 });
+		board.place(square.pos(), turn).expect("The game has already been replayed once.");
+	}
+
+	true
+}
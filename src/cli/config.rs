@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io;
 use toml;
+use score::Scoring;
 
 lazy_static! {
 	pub static ref CONFIG: Config = {
@@ -31,16 +32,37 @@ pub struct Graphics {
 	pub square_size: Option<u16>,
 	pub white_piece: String,
 	pub black_piece: String,
+	/// The pieces of the third and fourth player in games of Rolit.
+	pub extra_pieces: Option<Vec<String>>,
 	pub shadow: Option<String>,
 	pub white_score_colour: Vec<u8>,
 	pub black_score_colour: Vec<u8>
 }
 
+#[derive(Deserialize)]
+pub struct Rules {
+	pub scoring: Option<String>
+}
+
+impl Rules {
+	/// The way the pieces are counted at the end of a game.
+	pub fn scoring(&self) -> Scoring {
+		Scoring::from_config(&self.scoring)
+	}
+}
+
+#[derive(Deserialize)]
+pub struct Computer {
+	pub book: Option<String>
+}
+
 // Holds the client configuration.
 #[derive(Deserialize)]
 pub struct Config {
 	pub network: Network,
-	pub graphics: Graphics
+	pub graphics: Graphics,
+	pub rules: Rules,
+	pub computer: Computer
 }
 
 impl Config {
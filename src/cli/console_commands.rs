@@ -6,27 +6,212 @@ use std::time::Duration;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::{self, JoinHandle};
-use std::collections::VecDeque;
-
-use cli::{Game, OfflineGame, NetHandler, CONFIG};
+use std::collections::{HashMap, VecDeque};
+
+use ai::EndgameSolver;
+use board::{Board, Piece, valid_size};
+use cli::{AiGame, EngineKind, Game, OfflineGame, NetHandler, CONFIG, MAX_LEVEL};
+use cli::nethandler::Error as NetError;
+use handicap::{Handicap, MAX_CORNERS};
+use layout::Layout;
 use packets::*;
+use protocol::Capabilities;
+use rolit::MAX_PLAYERS;
+use start::{Start, DEFAULT_WINDOW};
+use rng::Rng;
+use score::Objective;
+use square::Square;
+use transcript::Transcript;
+
+/// The most empty squares a position may have to be solved from the console. Any more and the
+/// user will be waiting for a long time.
+const SOLVE_MAX_EMPTIES: u8 = 18;
 
 pub struct Context {
 	pub nethandler: Option<Arc<NetHandler>>,
 	pub client_list: Vec<(ClientId, String)>,
 	pub games: Vec<Box<Game>>,
-	pub packets: Arc<Mutex<VecDeque<Packet>>>
+	pub packets: Arc<Mutex<VecDeque<Packet>>>,
+	/// The options of the games other clients have requested, so they can be accepted as they are.
+	pub requests: HashMap<ClientId, GameOptions>,
+	/// The players of the games of Rolit the client has been invited to.
+	pub rolit_requests: Vec<Vec<ClientId>>
 }
 
 fn print_help() {
 	println!("help -- show this message");
-	println!("start -- Start a local game.");
+	println!("start [size] [misere] [blocked=<count|squares>] [start=<xot|moves|transcript>] [handicap=<black|white>:<corners>] -- Start a local game. The size of the board is written like 6x6 and defaults to 8x8. With misere, the player with the fewest pieces wins. Squares can be blocked at random, e.g. blocked=5, or chosen, e.g. blocked=c4,f6. The game can start from an XOT opening with start=xot, from a number of random moves that keep the game close to even with start=8, or start=8/40 to allow a larger advantage, or from moves like start=f5d6c3. A handicap like handicap=white:2 gives white two corners before the game starts.");
+	println!("start ai [black|white] [level] [search|mcts] [size] [blocked=<count|squares>] [start=<xot|moves|transcript>] [handicap=<black|white>:<corners>] -- Start a local game against the computer, playing as black or white. The level goes from 1 to {}. The computer either searches ahead or plays out random games (mcts).", MAX_LEVEL);
+	println!("setup <position> -- Start a local game from the position, e.g. ---------------------------WB------BW--------------------------- B. The size of the board follows from the number of squares.");
+	println!("solve <position> -- Calculate the perfect move and the final result of a position with at most {} empty squares.", SOLVE_MAX_EMPTIES);
+	println!("analyse <position> -- Show the mobility, frontier and stable pieces of both players and the regions of empty squares.");
+	println!("replay <transcript> -- Start a local game after making all moves of the transcript, e.g. f5d6c3.");
 	println!("connect <address> (<login_name>) -- Connect to the specified server.");
-	println!("challenge <name/id> -- Challenge the client with the provided name or id to a Duel or accept a request by them.");
+	println!("challenge <name/id> [size] [misere] [blocked=<count|squares>] [start=<xot|moves|transcript>] [handicap=<black|white>:<corners>] -- Challenge the client with the provided name or id to a Duel or accept a request by them. Without any options, a request is accepted as it is and a new challenge is a standard game on 8x8. The one whose challenge is accepted plays white and the one accepting it black, so handicap=white:2 gives the challenger two corners.");
+	println!("rolit <name/id> <name/id> [name/id] -- Invite two or three other clients to a game of Rolit, or accept the invitation of the client with only their name or id.");
 	println!("deny <name/id> -- Deny a game from the client, if the client had requested one.");
 	println!("exit -- End the program.");
 }
 
+/// Read a board size written like 6x6. Returns None if it is not a valid size.
+fn parse_size(arg: &str) -> Option<u8> {
+	let parts: Vec<&str> = arg.split('x').collect();
+	if parts.len() != 2 || parts[0] != parts[1] {
+		return None;
+	}
+
+	match parts[0].parse() {
+		Ok(size) if valid_size(size) => Some(size),
+		_ => None
+	}
+}
+
+/// Read the blocked squares written like blocked=5 for five random squares or blocked=c4,f6 for
+/// chosen ones. Returns None if the argument is no layout.
+fn parse_layout(arg: &str) -> Option<Layout> {
+	if !arg.starts_with("blocked=") {
+		return None;
+	}
+
+	let value = &arg["blocked=".len()..];
+	if let Ok(count) = value.parse() {
+		return Some(Layout::Random(count));
+	}
+
+	let mut squares = Vec::new();
+	for square in value.split(',') {
+		match square.parse::<Square>() {
+			Ok(square) => squares.push(square.pos()),
+			Err(_) => return None
+		}
+	}
+
+	Some(Layout::Squares(squares))
+}
+
+/// Read the start position written like start=xot for an XOT opening, start=8 for eight random
+/// moves, start=8/40 to allow the position to be further away from even or start=f5d6c3 for the
+/// moves provided. Returns None if the argument is no start position.
+fn parse_start(arg: &str) -> Option<Start> {
+	if !arg.starts_with("start=") {
+		return None;
+	}
+
+	let value = &arg["start=".len()..];
+	if value == "xot" {
+		return Some(Start::Xot);
+	}
+
+	let mut parts = value.splitn(2, '/');
+	if let Ok(moves) = parts.next().unwrap().parse() {
+		return match parts.next().map(|window| { window.parse() }) {
+			None => Some(Start::Random { moves: moves, window: DEFAULT_WINDOW }),
+			Some(Ok(window)) => Some(Start::Random { moves: moves, window: window }),
+			Some(Err(_)) => None
+		};
+	}
+
+	// Passes are made automatically, so the moves leave them out.
+	let transcript: Transcript = match value.parse() {
+		Ok(t) => t,
+		Err(_) => return None
+	};
+
+	let mut moves = Vec::with_capacity(transcript.len());
+	for m in transcript.moves() {
+		match m {
+			&Some(square) => moves.push(square.pos()),
+			&None => return None
+		}
+	}
+
+	Some(Start::Moves(moves))
+}
+
+/// Read the handicap written like handicap=white:2 for two corners for white. Returns None if the
+/// argument is no handicap.
+fn parse_handicap(arg: &str) -> Option<Handicap> {
+	if !arg.starts_with("handicap=") {
+		return None;
+	}
+
+	let mut parts = arg["handicap=".len()..].splitn(2, ':');
+	let piece = match parts.next() {
+		Some("black") => Piece::Black,
+		Some("white") => Piece::White,
+		_ => return None
+	};
+
+	match parts.next().map(|corners| { corners.parse() }) {
+		Some(Ok(corners)) if corners >= 1 && corners <= MAX_CORNERS => Some(Handicap::new(piece, corners)),
+		_ => None
+	}
+}
+
+/// Read the options of a game from arguments like 6x6, misere, blocked=5, start=xot and
+/// handicap=white:2, which may come in any order. Returns the first argument that could not be read as the error.
+fn parse_options(args: &[String]) -> Result<GameOptions, String> {
+	let mut options = GameOptions::new();
+	let mut size_arg = None;
+	let mut layout_arg = None;
+	let mut start_arg = None;
+	let mut handicap_arg = None;
+	for arg in args {
+		if arg == "misere" || arg == "misère" {
+			options = options.with_objective(Objective::FewestPieces);
+		}
+		else if arg.starts_with("blocked=") {
+			match parse_layout(arg) {
+				Some(layout) => options = options.with_layout(layout),
+				None => return Err(arg.clone())
+			}
+			layout_arg = Some(arg);
+		}
+		else if arg.starts_with("start=") {
+			match parse_start(arg) {
+				Some(start) => options = options.with_start(start),
+				None => return Err(arg.clone())
+			}
+			start_arg = Some(arg);
+		}
+		else if arg.starts_with("handicap=") {
+			match parse_handicap(arg) {
+				Some(handicap) => options = options.with_handicap(handicap),
+				None => return Err(arg.clone())
+			}
+			handicap_arg = Some(arg);
+		}
+		else {
+			match parse_size(arg) {
+				Some(size) => options = options.with_size(size),
+				None => return Err(arg.clone())
+			}
+			size_arg = Some(arg);
+		}
+	}
+
+	// The blocked squares, the start position and the handicap can only be checked once the size
+	// of the board is known. If they do not fit together, the argument that is most likely at
+	// fault is returned.
+	if options.valid() {
+		return Ok(options);
+	}
+
+	let culprit = if !options.layout.valid(options.size) {
+		layout_arg
+	}
+	else if start_arg.is_some() && options.clone().with_start(Start::Standard).valid() {
+		start_arg
+	}
+	else {
+		// Even the standard start cannot be played, so the handicap does not fit the blocked
+		// squares or the size of the board.
+		handicap_arg.or(layout_arg).or(size_arg).or(start_arg)
+	};
+
+	Err(culprit.cloned().unwrap_or_else(|| { args.join(" ") }))
+}
+
 fn find_name_or_id<'a>(client_list: &'a Vec<(ClientId, String)>, to_find: &String) -> Option<(ClientId, &'a String)> {
 	// Try find the client with the corresponding name.
 	for &(ref id, ref name) in client_list {
@@ -53,6 +238,7 @@ mod cmd {
 
 	pub enum Error {
 		WrongNumberOfArguments,
+		InvalidArgument(String),
 		NeedsConnection,
 		PlayerNotFound,
 		UnknownCommand(String)
@@ -99,6 +285,10 @@ mod cmd {
 				n.subscribe(Arc::downgrade(&ctx.packets));
 				Some(n)
 			},
+			Err(NetError::Incompatible(reason)) => {
+				println!("The server has refused the connection: {}", reason);
+				return Ok(());
+			},
 			Err(err) => {
 				println!("Could not connect to server {:?}", err);
 				return Ok(());
@@ -110,11 +300,154 @@ mod cmd {
 	}
 
 	pub fn start(ctx: &mut Context, args: Vec<String>) -> Result<(), Error> {
-		if !args.is_empty() {
+		if args.is_empty() || args[0] != "ai" {
+			let options = match parse_options(&args) {
+				Ok(options) => options,
+				Err(arg) => return Err(Error::InvalidArgument(arg))
+			};
+
+			ctx.games.push(Box::new(OfflineGame::new(options.resolved(&mut Rng::from_time()))));
+			return Ok(());
+		}
+
+		if args.len() > 8 {
 			return Err(Error::WrongNumberOfArguments);
 		}
 
-		ctx.games.push(Box::new(OfflineGame::new()));
+		// The colour, the level, the engine, the size and the blocked squares are all optional, so
+		// check what has been provided.
+		let mut piece = Piece::Black;
+		let mut level = 3;
+		let mut kind = EngineKind::Search;
+		let mut board_args = Vec::new();
+		for arg in &args[1..] {
+			match arg.as_str() {
+				"black" => piece = Piece::Black,
+				"white" => piece = Piece::White,
+				"search" => kind = EngineKind::Search,
+				"mcts" => kind = EngineKind::MonteCarlo,
+				arg if arg.contains('x') || arg.contains('=') => board_args.push(arg.to_string()),
+				arg => match arg.parse::<u8>() {
+					Ok(l) if l >= 1 && l <= MAX_LEVEL => level = l,
+					_ => return Err(Error::InvalidArgument(arg.to_string()))
+				}
+			}
+		}
+
+		let options = match parse_options(&board_args) {
+			Ok(options) => options.resolved(&mut Rng::from_time()),
+			Err(arg) => return Err(Error::InvalidArgument(arg))
+		};
+
+		ctx.games.push(Box::new(AiGame::new(piece, level, kind, options.board())));
+		Ok(())
+	}
+
+	pub fn setup(ctx: &mut Context, args: Vec<String>) -> Result<(), Error> {
+		if args.is_empty() {
+			return Err(Error::WrongNumberOfArguments);
+		}
+
+		let board: Board = match args.concat().parse() {
+			Ok(b) => b,
+			Err(err) => {
+				println!("Could not read position. {:?}", err);
+				return Ok(());
+			}
+		};
+
+		ctx.games.push(Box::new(OfflineGame::with_board(board, Objective::MostPieces)));
+		Ok(())
+	}
+
+	pub fn solve(_: &mut Context, args: Vec<String>) -> Result<(), Error> {
+		if args.is_empty() {
+			return Err(Error::WrongNumberOfArguments);
+		}
+
+		let board: Board = match args.concat().parse() {
+			Ok(b) => b,
+			Err(err) => {
+				println!("Could not read position. {:?}", err);
+				return Ok(());
+			}
+		};
+
+		let solution = match EndgameSolver::new(SOLVE_MAX_EMPTIES).solve(&board) {
+			Some(s) => s,
+			None => {
+				println!("The position has too many empty squares to be solved.");
+				return Ok(());
+			}
+		};
+
+		// The difference is from the view of the player whos turn it is.
+		let (turn, other) = match board.turn() {
+			Piece::Black => ("Black", "White"),
+			Piece::White => ("White", "Black")
+		};
+		if solution.diff > 0 {
+			println!("{} wins by {} with perfect play.", turn, solution.diff);
+		}
+		else if solution.diff < 0 {
+			println!("{} wins by {} with perfect play.", other, -solution.diff);
+		}
+		else {
+			println!("The game ends in a draw with perfect play.");
+		}
+
+		if let Some(best) = solution.best {
+			println!("The best move for {} is {}.", turn, Square::from(best));
+		}
+		Ok(())
+	}
+
+	pub fn analyse(_: &mut Context, args: Vec<String>) -> Result<(), Error> {
+		if args.is_empty() {
+			return Err(Error::WrongNumberOfArguments);
+		}
+
+		let board: Board = match args.concat().parse() {
+			Ok(b) => b,
+			Err(err) => {
+				println!("Could not read position. {:?}", err);
+				return Ok(());
+			}
+		};
+
+		let analysis = board.analyse();
+		println!("                     Black  White");
+		println!("Mobility:            {:5}  {:5}", analysis.black.mobility, analysis.white.mobility);
+		println!("Potential mobility:  {:5}  {:5}", analysis.black.potential_mobility, analysis.white.potential_mobility);
+		println!("Frontier:            {:5}  {:5}", analysis.black.frontier, analysis.white.frontier);
+		println!("Stable:              {:5}  {:5}", analysis.black.stable, analysis.white.stable);
+
+		let sizes: Vec<String> = analysis.regions.iter().map(|r| { r.count_ones().to_string() }).collect();
+		println!("Empty regions: {} ({} odd), sized {}", analysis.regions.len(), analysis.odd_regions(), sizes.join(", "));
+		Ok(())
+	}
+
+	pub fn replay(ctx: &mut Context, args: Vec<String>) -> Result<(), Error> {
+		if args.is_empty() {
+			return Err(Error::WrongNumberOfArguments);
+		}
+
+		// The transcript may have been entered with spaces between the moves.
+		let transcript: Transcript = match args.concat().parse() {
+			Ok(t) => t,
+			Err(err) => {
+				println!("Could not read transcript. {:?}", err);
+				return Ok(());
+			}
+		};
+
+		let mut board = Board::new();
+		if let Err(err) = board.replay(&transcript) {
+			println!("Could not replay transcript. {:?}", err);
+			return Ok(());
+		}
+
+		ctx.games.push(Box::new(OfflineGame::with_board(board, Objective::MostPieces)));
 		Ok(())
 	}
 
@@ -122,7 +455,7 @@ mod cmd {
 		if ctx.nethandler.is_none() {
 			return Err(Error::NeedsConnection);
 		}
-		if args.len() != 1 {
+		if args.len() < 1 || args.len() > 6 {
 			return Err(Error::WrongNumberOfArguments);
 		}
 
@@ -135,8 +468,64 @@ mod cmd {
 		}
 		let (id, name) = client.unwrap();
 
-		ctx.nethandler.as_ref().unwrap().send(&Packet::RequestGame(id));
-		println!("Requested game from client [{}]: {}", id, name);
+		// Unless the user wants something else, accept the options of the other clients request.
+		let options = if args.len() > 1 {
+			match parse_options(&args[1..]) {
+				Ok(options) => options,
+				Err(arg) => return Err(Error::InvalidArgument(arg))
+			}
+		}
+		else { ctx.requests.get(&id).cloned().unwrap_or(GameOptions::new()) };
+
+		if options != GameOptions::new() && !ctx.nethandler.as_ref().unwrap().server_capabilities().contains(Capabilities::VARIANTS) {
+			println!("The server only supports games with the standard options.");
+			return Ok(());
+		}
+
+		println!("Requested game on a {} from client [{}]: {}", options, id, name);
+		ctx.nethandler.as_ref().unwrap().send(&Packet::RequestGame(id, options));
+		Ok(())
+	}
+
+	pub fn rolit(ctx: &mut Context, args: Vec<String>) -> Result<(), Error> {
+		if ctx.nethandler.is_none() {
+			return Err(Error::NeedsConnection);
+		}
+		if args.is_empty() || args.len() > MAX_PLAYERS as usize - 1 {
+			return Err(Error::WrongNumberOfArguments);
+		}
+		if !ctx.nethandler.as_ref().unwrap().server_capabilities().contains(Capabilities::ROLIT) {
+			println!("The server does not support games of Rolit.");
+			return Ok(());
+		}
+
+		let mut ids = Vec::with_capacity(args.len());
+		for arg in &args {
+			match find_name_or_id(&ctx.client_list, arg) {
+				Some((id, _)) => ids.push(id),
+				None => return Err(Error::PlayerNotFound)
+			}
+		}
+
+		// A single player is the one whos invitation is accepted, otherwise the others are invited
+		// with the client starting the game.
+		let players = if ids.len() == 1 {
+			match ctx.rolit_requests.iter().find(|players| { players[0] == ids[0] }) {
+				Some(players) => players.clone(),
+				None => {
+					println!("Client [{}] has not invited you to a game of Rolit.", ids[0]);
+					return Ok(());
+				}
+			}
+		}
+		else {
+			let mut players = vec![ctx.nethandler.as_ref().unwrap().id()];
+			players.extend(ids);
+			players
+		};
+
+		println!("Requested game of Rolit with {:?}", players);
+		ctx.nethandler.as_ref().unwrap().send(&Packet::RequestRolit(players));
 		Ok(())
 	}
 
@@ -260,7 +649,12 @@ impl Console {
 		let res = match &raw.as_str() {
 			&"connect" => cmd::connect(context, cmd),
 			&"start" => cmd::start(context, cmd),
+			&"setup" => cmd::setup(context, cmd),
+			&"solve" => cmd::solve(context, cmd),
+			&"analyse" => cmd::analyse(context, cmd),
+			&"replay" => cmd::replay(context, cmd),
 			&"challenge" => cmd::challenge(context, cmd),
+			&"rolit" => cmd::rolit(context, cmd),
 			&"deny" => cmd::deny(context, cmd),
 			&"help" => { print_help(); Ok(()) },
 			c => Err(cmd::Error::UnknownCommand(c.to_string()))
@@ -269,6 +663,7 @@ impl Console {
 		match res {
 			Ok(()) => {},
 			Err(cmd::Error::WrongNumberOfArguments) => println!("Wrong number of arguments. See 'help' for usage information."),
+			Err(cmd::Error::InvalidArgument(arg)) => println!("Invalid argument '{}'. See 'help' for usage information.", arg),
 			Err(cmd::Error::NeedsConnection) => println!("You need to be connected to a Server for this."),
 			Err(cmd::Error::PlayerNotFound) => println!("Could not find player. Please make sure the id or name is valid."),
 			Err(cmd::Error::UnknownCommand(c)) => println!("Unknown command '{}'. See 'help' for options.", c)
@@ -293,3 +688,24 @@ impl Drop for Console {
 		// self.handle.take().unwrap().join().expect("Could not join input thread properly.");
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(args: &[&str]) -> Result<GameOptions, String> {
+		parse_options(&args.iter().map(|arg| { arg.to_string() }).collect::<Vec<String>>())
+	}
+
+	#[test]
+	fn invalid_options_name_the_argument() {
+		assert!(parse(&["6x6", "misere"]).is_ok());
+
+		// The corner of the handicap is blocked.
+		assert_eq!(parse(&["blocked=a1", "handicap=black:1"]), Err("handicap=black:1".to_string()));
+		// A chosen start position needs chosen blocked squares.
+		assert_eq!(parse(&["blocked=3", "start=f5"]), Err("start=f5".to_string()));
+		// The move is not on a 4x4 board.
+		assert_eq!(parse(&["4x4", "start=f5"]), Err("start=f5".to_string()));
+	}
+}
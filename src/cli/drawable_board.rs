@@ -1,20 +1,117 @@
 use std::ops::{Deref, DerefMut};
+use std::sync::mpsc::Receiver;
 use board::*;
+use events::{BoardEvent, ObservedBoard};
 use config::CONFIG;
-use sfml::graphics::{Color, Texture, Drawable, RectangleShape, RenderTarget, RenderStates, Shape, Sprite, Transformable};
+use rolit::RolitBoard;
+use score::Objective;
+use sfml::graphics::{Color, Texture, Drawable, Rect, RectangleShape, RenderTarget, RenderStates, Shape, Sprite, Transformable};
+
+/// Everything that has to be known about a board to draw it, so boards with different rules can
+/// be drawn the same way.
+pub trait BoardView {
+	/// The side length of the board.
+	fn board_size(&self) -> u8;
+	/// The texture of the piece on the square, if there is one. Black is 0, white 1 and every
+	/// colour after that is one of the extra pieces.
+	fn piece_texture(&self, square: (u8, u8)) -> Option<usize>;
+	fn square_blocked(&self, square: (u8, u8)) -> bool;
+	/// The squares the player whos turn it is can place a piece on.
+	fn hints(&self) -> Vec<(u8, u8)>;
+	/// The square of the last piece that has been placed on the board.
+	fn last_placed(&self) -> Option<(u8, u8)>;
+}
+
+impl BoardView for ObservedBoard {
+	fn board_size(&self) -> u8 {
+		self.size()
+	}
+
+	fn piece_texture(&self, square: (u8, u8)) -> Option<usize> {
+		match self.square(square) {
+			Some(Piece::Black) => Some(0),
+			Some(Piece::White) => Some(1),
+			None => None
+		}
+	}
+
+	fn square_blocked(&self, square: (u8, u8)) -> bool {
+		self.is_blocked(square)
+	}
+
+	// TODO: This should probably better be handled by the running game, not the board,
+	// since it's better to only show your opportunities in an online game.
+	fn hints(&self) -> Vec<(u8, u8)> {
+		self.opportunities(self.turn())
+	}
+
+	fn last_placed(&self) -> Option<(u8, u8)> {
+		self.history().iter().rev().filter_map(|m| {
+			match m {
+				&Move::Place { square, .. } => Some(square),
+				&Move::Pass(_) => None
+			}
+		}).next()
+	}
+}
+
+impl BoardView for RolitBoard {
+	fn board_size(&self) -> u8 {
+		self.size()
+	}
+
+	fn piece_texture(&self, square: (u8, u8)) -> Option<usize> {
+		self.square(square).map(|colour| { colour as usize })
+	}
+
+	fn square_blocked(&self, _: (u8, u8)) -> bool {
+		false
+	}
+
+	fn hints(&self) -> Vec<(u8, u8)> {
+		self.opportunities(self.turn())
+	}
+
+	fn last_placed(&self) -> Option<(u8, u8)> {
+		RolitBoard::last_placed(self)
+	}
+}
 
 /// Client ontly wrapper for the board, where the board has to be rendered.
-pub struct DrawableBoard {
+pub struct DrawableBoard<B: BoardView = ObservedBoard> {
 	board_tex: Texture,
 	board_light_tex: Texture,
 	white_piece_tex: Texture,
 	black_piece_tex: Texture,
+	// The pieces of the colours after black and white, for games with more than two players.
+	extra_piece_tex: Vec<Texture>,
 	shadow_tex: Option<Texture>, // The shadow that will be drawn around each piece.
-	inner: Board
+	// Only boards that can be observed send events, others are checked when updating.
+	events: Option<Receiver<BoardEvent>>,
+	// The square of the last piece that has been placed, which is highlighted.
+	last_move: Option<(u8, u8)>,
+	inner: B
 }
 
-impl DrawableBoard {
-	pub fn new(board: Board) -> Option<DrawableBoard> {
+impl DrawableBoard<ObservedBoard> {
+	pub fn new(board: Board, objective: Objective) -> Option<DrawableBoard> {
+		let mut board = ObservedBoard::new(board).with_objective(objective).with_scoring(CONFIG.rules.scoring());
+		let events = board.events();
+		DrawableBoard::load(board, Some(events), 0)
+	}
+}
+
+impl DrawableBoard<RolitBoard> {
+	/// Draw a game of Rolit, which needs an extra piece for every player after the second.
+	pub fn rolit(board: RolitBoard) -> Option<DrawableBoard<RolitBoard>> {
+		let extra_pieces = board.players() as usize - 2;
+		DrawableBoard::load(board, None, extra_pieces)
+	}
+}
+
+impl<B: BoardView> DrawableBoard<B> {
+	/// Load all textures, including the number of extra pieces provided.
+	fn load(board: B, events: Option<Receiver<BoardEvent>>, extra_pieces: usize) -> Option<DrawableBoard<B>> {
 		let board_tex = match Texture::from_file(&CONFIG.graphics.board) {
 			Some(t) => t,
 			None => {
@@ -85,13 +182,35 @@ impl DrawableBoard {
 			else { None }
 		};
 
+		let extra_files = CONFIG.graphics.extra_pieces.as_ref().map(|files| { files.as_slice() }).unwrap_or(&[]);
+		if extra_files.len() < extra_pieces {
+			println!("Could not load the extra pieces. {} are needed, but only {} are configured.", extra_pieces, extra_files.len());
+			return None;
+		}
+
+		let mut extra_piece_tex = Vec::with_capacity(extra_pieces);
+		for file in &extra_files[..extra_pieces] {
+			match Texture::from_file(file) {
+				Some(t) => extra_piece_tex.push(t),
+				None => {
+					println!("Could not load extra piece texture {}.", file);
+					return None;
+				}
+			}
+		}
+
+		let last_move = board.last_placed();
+
 		// Buffer drawable board.
 		let db = DrawableBoard {
 			board_tex: board_tex,
 			board_light_tex: board_light_tex,
 			white_piece_tex: white_piece_tex,
 			black_piece_tex: black_piece_tex,
+			extra_piece_tex: extra_piece_tex,
 			shadow_tex: shadow_tex,
+			events: events,
+			last_move: last_move,
 			inner: board
 		};
 
@@ -106,39 +225,88 @@ impl DrawableBoard {
 			return None;
 		}
 
+		if db.extra_piece_tex.iter().any(|tex| { tex.size().x as u16 != db.piece_size() }) {
+			println!("Extra pieces do not have the right dimensions ({}x{}).", db.piece_size(), db.piece_size());
+			return None;
+		}
+
 		// Everything loaded, all checks have passed.
 		Some(db)
 	}
 
+	/// Catch up with the changes that have been made to the board since the last update.
+	pub fn update(&mut self) {
+		let mut last_move = self.last_move;
+		match self.events {
+			Some(ref events) => while let Ok(event) = events.try_recv() {
+				match event {
+					BoardEvent::Placed { square, .. } => last_move = Some(square),
+					BoardEvent::Undone(_) | BoardEvent::Redone(_) => last_move = self.inner.last_placed(),
+					_ => {}
+				}
+			},
+			None => last_move = self.inner.last_placed()
+		}
+
+		self.last_move = last_move;
+	}
+
 	/// The size (width and height are the same) of the entire board.
 	pub fn size(&self) -> u32 {
-		self.board_tex.size().x as u32
+		self.piece_size() as u32 * self.inner.board_size() as u32
 	}
 
 	/// Get the size (width and height are the same) of an individual board piece.
 	pub fn piece_size(&self) -> u16 {
 		match CONFIG.graphics.square_size {
 			Some(size) => size,
-			None => (self.board_tex.size().x / 8) as u16
+			None => (self.board_tex.size().x / DEFAULT_SIZE as u32) as u16
 		}
 	}
 
 	/// Translates a position (usually of the mouse cursor) and translates it
 	/// to the indices of the corresponding piece.
 	pub fn piece_index(&self, x: u32, y: u32) -> (u8, u8) {
-		let x = (x/64) as u8;
-		let y = (y/64) as u8;
+		let x = (x / self.piece_size() as u32) as u8;
+		let y = (y / self.piece_size() as u32) as u8;
 
 		(x, y)
 	}
+
+	/// Draw the empty board. The texture shows the standard board, so on boards of other sizes one
+	/// of its inner squares is repeated instead.
+	fn draw_background(&self, target: &mut RenderTarget) {
+		if self.inner.board_size() == DEFAULT_SIZE {
+			target.draw(&Sprite::with_texture(&self.board_tex));
+			return;
+		}
+
+		let tex_square = (self.board_tex.size().x / DEFAULT_SIZE as u32) as i32;
+		let scale = self.piece_size() as f32 / tex_square as f32;
+		let mut square_spr = Sprite::with_texture(&self.board_tex);
+		square_spr.set_texture_rect(&Rect::<i32> {
+			left: 3 * tex_square,
+			top: 3 * tex_square,
+			width: tex_square,
+			height: tex_square
+		});
+		square_spr.set_scale2f(scale, scale);
+
+		let size = self.piece_size();
+		for x in 0..self.inner.board_size() as u16 {
+			for y in 0..self.inner.board_size() as u16 {
+				square_spr.set_position2f((x * size) as f32, (y * size) as f32);
+				target.draw(&square_spr);
+			}
+		}
+	}
 }
 
-impl Drawable for DrawableBoard {
+impl<B: BoardView> Drawable for DrawableBoard<B> {
 	fn draw<'se, 'tex, 'sh, 'shte>(&'se self, target: &mut RenderTarget, _: RenderStates<'tex, 'sh, 'shte>)
 	where 'se: 'sh {
 		// Draw the underlying board.
-		let board_spr = Sprite::with_texture(&self.board_tex);
-		target.draw(&board_spr);
+		self.draw_background(target);
 
 		// TODO: Redo the loop with OpenGL-calls, which have way less overhead.
 
@@ -146,19 +314,30 @@ impl Drawable for DrawableBoard {
 		// all shadows can later be drawn before all sprites.
 		let mut pieces: Vec<(Sprite, Option<Sprite>)> = Vec::new();
 
-		// TODO: This should probably better be handled by the running game, not the board,
-		// since it's better to only show your opportunities in an online game.
-		let opportunities = self.opportunities(self.turn());
+		let opportunities = self.inner.hints();
 
 		// Convert the opportunities Vector into a Two-Dimensional vector, to reduce overhead
 		// in the loop. up ahead.
-		let mut opportunities2d = vec![vec![false; 8]; 8];
+		let board_size = self.inner.board_size() as usize;
+		let mut opportunities2d = vec![vec![false; board_size]; board_size];
 		for (x, y) in opportunities {
 			opportunities2d[x as usize][y as usize] = true;
 		}
 
-		for x in 0..8 {
-			for y in 0..8 {
+		for x in 0..board_size {
+			for y in 0..board_size {
+				// Blocked squares are covered up, since nothing can ever be placed on them.
+				if self.inner.square_blocked((x as u8, y as u8)) {
+					let mut rect = RectangleShape::new();
+					rect.set_fill_color(&Color::rgba(40, 40, 40, 220));
+					rect.set_size2f(self.piece_size() as f32, self.piece_size() as f32);
+
+					let size = self.piece_size();
+					rect.set_position2f((x as u16 * size) as f32, (y as u16 * size) as f32);
+					target.draw(&rect);
+					continue;
+				}
+
 				// If there is an opportunity for the current player here, show it.
 				if opportunities2d[x][y] == true {
 					let mut rect = RectangleShape::new();
@@ -174,14 +353,22 @@ impl Drawable for DrawableBoard {
 					target.draw(&rect);
 				}
 
+				// Highlight the square the last piece has been placed on.
+				if self.last_move == Some((x as u8, y as u8)) {
+					let mut rect = RectangleShape::new();
+					rect.set_fill_color(&Color::rgba(255, 255, 255, 40));
+					rect.set_size2f(self.piece_size() as f32, self.piece_size() as f32);
+
+					let size = self.piece_size();
+					rect.set_position2f((x as u16 * size) as f32, (y as u16 * size) as f32);
+					target.draw(&rect);
+				}
+
 				// Check if a piece is at this position and create it.
-				let mut sprite = match self.squares()[x][y] {
-					Some(Piece::White) => {
-						Sprite::with_texture(&self.white_piece_tex)
-					},
-					Some(Piece::Black) => {
-						Sprite::with_texture(&self.black_piece_tex)
-					},
+				let mut sprite = match self.inner.piece_texture((x as u8, y as u8)) {
+					Some(0) => Sprite::with_texture(&self.black_piece_tex),
+					Some(1) => Sprite::with_texture(&self.white_piece_tex),
+					Some(i) => Sprite::with_texture(&self.extra_piece_tex[i - 2]),
 					None => continue
 				};
 
@@ -230,23 +417,25 @@ impl Drawable for DrawableBoard {
 			target.draw(&piece);
 		}
 
-		// Draw the light on the board over everything.
+		// Draw the light on the board over everything, stretched to the size of the board.
+		let light_scale = self.size() as f32 / self.board_light_tex.size().x as f32;
 		let mut board_light_spr = Sprite::with_texture(&self.board_light_tex);
+		board_light_spr.set_scale2f(light_scale, light_scale);
 		board_light_spr.set_color(&Color::rgba(255, 255, 255, (CONFIG.graphics.board_light_opacity as f32 / 100.0 * 255.0) as u8));
 		target.draw(&board_light_spr);
 	}
 }
 
-impl Deref for DrawableBoard {
-	type Target = Board;
+impl<B: BoardView> Deref for DrawableBoard<B> {
+	type Target = B;
 
-	fn deref(&self) -> &Board {
+	fn deref(&self) -> &B {
 		&self.inner
 	}
 }
 
-impl DerefMut for DrawableBoard {
-	fn deref_mut(&mut self) -> &mut Board {
+impl<B: BoardView> DerefMut for DrawableBoard<B> {
+	fn deref_mut(&mut self) -> &mut B {
 		&mut self.inner
 	}
 }
@@ -24,7 +24,12 @@ impl<'a> DrawableScore<'a> {
 impl<'a> Drawable for DrawableScore<'a> {
 	fn draw<'se, 'tex, 'sh, 'shte>(&'se self, target: &mut RenderTarget, _: RenderStates<'tex, 'sh, 'shte>)
 	where 'se: 'sh {
-		let (white, black) = self.get_score();
+		// Once the game is over, the bar shows the final result, which depending on the scoring
+		// might differ from the pieces on the board.
+		let (white, black) = match self.result() {
+			Some(result) => (result.white(), result.black()),
+			None => self.get_score()
+		};
 
 		let white_length = self.bounds.width as f32 * white as f32 / (white + black) as f32;
 		let mut white_bar = RectangleShape::with_size(&Vector2f::new(white_length, self.bounds.height as f32));
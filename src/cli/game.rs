@@ -2,9 +2,15 @@ use sfml::window::{ContextSettings, Event, VideoMode, style};
 use sfml::window::mouse::Button;
 use sfml::graphics::{Color, RenderTarget, RenderWindow, Rect};
 use std::sync::Arc;
-use cli::{DrawableBoard, DrawableScore, SCORE_HEIGHT, NetHandler};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use cli::{DrawableBoard, DrawableScore, SCORE_HEIGHT, NetHandler, CONFIG};
+use ai::*;
 use board::*;
+use rolit::*;
 use score::*;
+use square::Square;
 use packets::*;
 
 pub trait Game {
@@ -14,32 +20,137 @@ pub trait Game {
 	fn draw(&mut self);
 }
 
-fn initialise_graphics() -> (DrawableBoard, RenderWindow) {
+fn initialise_graphics(board: Board, objective: Objective) -> (DrawableBoard, RenderWindow) {
 	// Create the board this game will be played in.
-	let board = DrawableBoard::new(Board::new()).unwrap();
+	let board = DrawableBoard::new(board, objective).unwrap();
 
-	// Create the window for the game.
-	let mut window = RenderWindow::new(VideoMode::new(board.size(), board.size() + SCORE_HEIGHT, 32), "SFML Othello", style::CLOSE, &ContextSettings::default()).unwrap();
+	// Create the window for the game. Misère games are marked in the title, since the board looks
+	// the same.
+	let title = match objective {
+		Objective::MostPieces => "SFML Othello",
+		Objective::FewestPieces => "SFML Othello (misère)"
+	};
+	let mut window = RenderWindow::new(VideoMode::new(board.size(), board.size() + SCORE_HEIGHT, 32), title, style::CLOSE, &ContextSettings::default()).unwrap();
 	window.set_framerate_limit(20);
 
+	(board, window)
+}
 
+/// Let the player know which corners the handicap of the game has given to whom.
+fn announce_handicap(options: &GameOptions, piece: Piece) {
+	if let Some(handicap) = options.handicap {
+		let corners: Vec<String> = handicap.squares(options.size).into_iter().map(|square| { Square::from(square).to_string() }).collect();
+		if handicap.piece == piece {
+			println!("You start with a handicap, the corners {} are yours.", corners.join(", "));
+		}
+		else {
+			println!("Your opponent starts with a handicap, the corners {} are theirs.", corners.join(", "));
+		}
+	}
+}
 
-	(board, window)
+/// Let the players know in case the last move on the board was a pass. Passes are made by the board
+/// automatically, whenever a player cannot place a piece anywhere.
+fn announce_pass(board: &Board) {
+	if let Some(&Move::Pass(piece)) = board.history().last() {
+		match piece {
+			Piece::White => println!("White has to pass."),
+			Piece::Black => println!("Black has to pass.")
+		}
+	}
+}
+
+/// Let the players know who wins the game, unless it is the usual.
+fn announce_objective(objective: Objective) {
+	if objective == Objective::FewestPieces {
+		println!("This game is played with misère rules. The player with the fewest pieces wins.");
+	}
+}
+
+/// Print the result in case the game on the board is over. Returns true if it is.
+fn announce_result(board: &Board, objective: Objective) -> bool {
+	let score = Score::with_scoring(board, CONFIG.rules.scoring()).with_objective(objective);
+	let result = match score.result() {
+		Some(result) => result,
+		None => return false
+	};
+
+	match result {
+		GameResult::WhiteWins { black, white } => println!("White has won! {}:{}", white, black),
+		GameResult::BlackWins { black, white } => println!("Black has won! {}:{}", black, white),
+		GameResult::Draw { black, white } => println!("The game has ended in a draw. {}:{}", white, black)
+	}
+
+	println!("Transcript: {}", board.transcript());
+	true
+}
+
+/// Print the result from the view of the player with the piece, in case the game on the board is
+/// over. Returns true if it is.
+fn announce_result_for(board: &Board, piece: Piece, objective: Objective) -> bool {
+	let score = Score::with_scoring(board, CONFIG.rules.scoring()).with_objective(objective);
+	let result = match score.result() {
+		Some(result) => result,
+		None => return false
+	};
+
+	let (own, opponent) = match piece {
+		Piece::Black => (result.black(), result.white()),
+		Piece::White => (result.white(), result.black())
+	};
+
+	match result.winner() {
+		Some(p) if p == piece => println!("You have won! {}:{}", own, opponent),
+		Some(_) => println!("You have lost. {}:{}", own, opponent),
+		None => println!("The game has ended in a draw. {}:{}", own, opponent)
+	}
+
+	println!("Transcript: {}", board.transcript());
+	true
+}
+
+/// Draw the board with the score bar below it into the window.
+fn draw_game(window: &mut RenderWindow, board: &mut DrawableBoard, objective: Objective) {
+	board.update();
+
+	window.clear(&Color::rgb(100, 200, 100));
+	window.draw(board);
+
+	// Create the Score Bar
+	let score_size = Rect::<u32> {
+		left: 0,
+		top: board.size(),
+		width: board.size(),
+		height: SCORE_HEIGHT
+	};
+	let score = DrawableScore::new(Score::with_scoring(board, CONFIG.rules.scoring()).with_objective(objective), score_size);
+	window.draw(&score);
+
+	window.display();
 }
 
 pub struct OfflineGame {
 	board: DrawableBoard,
+	objective: Objective,
 	window: RenderWindow,
 	running: bool
 }
 
 
 impl OfflineGame {
-	pub fn new() -> OfflineGame {
-		let (board, window) = initialise_graphics();
+	/// Start a game with the options provided, which have to be resolved.
+	pub fn new(options: GameOptions) -> OfflineGame {
+		OfflineGame::with_board(options.board(), options.objective)
+	}
+
+	/// Start the game on a board that may already have some moves made on it.
+	pub fn with_board(board: Board, objective: Objective) -> OfflineGame {
+		let (board, window) = initialise_graphics(board, objective);
+		announce_objective(objective);
 
 		OfflineGame {
 			board: board,
+			objective: objective,
 			window: window,
 			running: true
 		}
@@ -56,29 +167,14 @@ impl Game for OfflineGame {
 				if button == Button::Left {
 					let pos = self.board.piece_index(x as u32, y as u32);
 					let turn = self.board.turn();
-					if self.board.place(pos, turn) {
-						let score = Score::score(&self.board);
-						match score.winner() {
-							Some(p) => {
-								match p {
-									Piece::White => println!("White has won! {}:{}", score.white(), score.black()),
-									Piece::Black => println!("Black has won! {}:{}", score.black(), score.white()),
-								}
-
-								self.running = false;
-							},
-							None => {}
+					if self.board.place(pos, turn).is_ok() {
+						announce_pass(&self.board);
+
+						if announce_result(&self.board, self.objective) {
+							self.running = false;
 						}
 					}
 				}
-				else if button == Button::Right {
-					match self.board.turn() {
-						Piece::White => println!("White has passed"),
-						Piece::Black => println!("Black has passed")
-					}
-
-					self.board.pass();
-				}
 			}
 		}
 	}
@@ -90,25 +186,13 @@ impl Game for OfflineGame {
 	}
 
 	fn draw(&mut self) {
-		self.window.clear(&Color::rgb(100, 200, 100));
-		self.window.draw(&self.board);
-
-		// Create the Score Bar
-		let score_size = Rect::<u32> {
-			left: 0,
-			top: self.board.size(),
-			width: self.board.size(),
-			height: SCORE_HEIGHT
-		};
-		let score = DrawableScore::new(Score::score(&self.board), score_size);
-		self.window.draw(&score);
-
-		self.window.display();
+		draw_game(&mut self.window, &mut self.board, self.objective);
 	}
 }
 
 pub struct OnlineGame {
 	piece: Piece,
+	objective: Objective,
 	opponent: ClientId,
 	nethandler: Arc<NetHandler>,
 	board: DrawableBoard,
@@ -117,11 +201,14 @@ pub struct OnlineGame {
 }
 
 impl OnlineGame {
-	pub fn new(nethandler: Arc<NetHandler>, piece: Piece, opponent: ClientId) -> OnlineGame {
-		let (board, window) = initialise_graphics();
+	pub fn new(nethandler: Arc<NetHandler>, piece: Piece, opponent: ClientId, options: GameOptions) -> OnlineGame {
+		let (board, window) = initialise_graphics(options.board(), options.objective);
+		announce_objective(options.objective);
+		announce_handicap(&options, piece);
 
 		OnlineGame {
 			piece: piece,
+			objective: options.objective,
 			opponent: opponent,
 			nethandler: nethandler,
 			board: board,
@@ -129,11 +216,23 @@ impl OnlineGame {
 			running: true
 		}
 	}
+
+	/// Check the status of the board after a piece has been placed and end the game, in case
+	/// neither player can place any more pieces.
+	fn check_status(&mut self) {
+		announce_pass(&self.board);
+
+		if announce_result_for(&self.board, self.piece, self.objective) {
+			self.running = false;
+		}
+	}
 }
 
 impl Game for OnlineGame {
 	fn handle_events(&mut self) {
-		for event in self.window.events() {
+		// The events borrow the window, so they are collected before any of them is handled.
+		let events: Vec<Event> = self.window.events().collect();
+		for event in events {
 			if let Event::Closed = event {
 				self.running = false;
 
@@ -144,19 +243,14 @@ impl Game for OnlineGame {
 			else if let Event::MouseButtonPressed {button, x, y} = event {
 				if button == Button::Left {
 					let pos = self.board.piece_index(x as u32, y as u32);
-					if self.board.place(pos, self.piece) {
-						// Send the move to the server.
-						self.nethandler.send(&Packet::PlacePiece(self.opponent, pos.0, pos.1));
-					}
-				}
-				else if button == Button::Right {
-					if self.board.turn() != self.piece {
-						println!("You cannot pass at the moment, because it's not your turn.");
-					}
-					else {
-						println!("Passing.");
-						self.board.pass();
-						self.nethandler.send(&Packet::Pass(self.opponent));
+					match self.board.place(pos, self.piece) {
+						Ok(_) => {
+							// Send the move to the server.
+							self.nethandler.send(&Packet::PlacePiece(self.opponent, pos.0, pos.1));
+							self.check_status();
+						},
+						Err(PlaceError::WrongTurn) => println!("It is your opponents turn."),
+						Err(_) => {}
 					}
 				}
 			}
@@ -172,7 +266,10 @@ impl Game for OnlineGame {
 					return false;
 				}
 
-				self.board.place((x, y), self.piece.opposite());
+				if let Err(err) = self.board.place((x, y), self.piece.opposite()) {
+					println!("[WARNING] Your opponent has made an illegal move at {}. {:?}", Square::from((x, y)), err);
+				}
+				self.check_status();
 				true
 			},
 			&Packet::Pass(opponent) => {
@@ -180,8 +277,12 @@ impl Game for OnlineGame {
 					return false;
 				}
 
-				self.board.pass();
-				println!("Your opponent has passed.");
+				if self.board.pass() {
+					println!("Your opponent has passed.");
+				}
+				else {
+					println!("[WARNING] Your opponent has passed, although they could still place a piece.");
+				}
 				true
 			},
 			&Packet::AbandonGame(opponent) => {
@@ -202,19 +303,337 @@ impl Game for OnlineGame {
 	}
 
 	fn draw(&mut self) {
+		draw_game(&mut self.window, &mut self.board, self.objective);
+	}
+}
+
+/// A game of Rolit on the server, played by three or four clients. There is no score bar, the
+/// pieces are counted once the board is full.
+pub struct RolitGame {
+	colour: Colour,
+	/// The ids of all players in turn order, the first one identifies the game on the server.
+	players: Vec<ClientId>,
+	nethandler: Arc<NetHandler>,
+	board: DrawableBoard<RolitBoard>,
+	window: RenderWindow,
+	running: bool
+}
+
+impl RolitGame {
+	pub fn new(nethandler: Arc<NetHandler>, players: Vec<ClientId>) -> RolitGame {
+		let colour = players.iter().position(|&id| { id == nethandler.id() }).expect("The client is not one of the players.") as Colour;
+		let board = DrawableBoard::rolit(RolitBoard::new(DEFAULT_SIZE, players.len() as u8)).unwrap();
+
+		let mut window = RenderWindow::new(VideoMode::new(board.size(), board.size(), 32), "SFML Rolit", style::CLOSE, &ContextSettings::default()).unwrap();
+		window.set_framerate_limit(20);
+
+		println!("Starting a game of Rolit with {} players. You are playing {}.", players.len(), COLOUR_NAMES[colour as usize]);
+
+		RolitGame {
+			colour: colour,
+			players: players,
+			nethandler: nethandler,
+			board: board,
+			window: window,
+			running: true
+		}
+	}
+
+	/// The id the server uses to refer to this game.
+	fn host(&self) -> ClientId {
+		self.players[0]
+	}
+
+	/// End the game and count the pieces once the board is full.
+	fn check_status(&mut self) {
+		if !self.board.finished() {
+			return;
+		}
+
+		for colour in 0..self.board.players() {
+			println!("{} [{}]: {}", COLOUR_NAMES[colour as usize], self.players[colour as usize], self.board.count(colour));
+		}
+
+		let leaders = self.board.leaders();
+		if leaders == vec![self.colour] {
+			println!("You have won.");
+		}
+		else if leaders.contains(&self.colour) {
+			println!("You have tied for the win.");
+		}
+		else {
+			println!("You have lost.");
+		}
+
+		self.running = false;
+	}
+}
+
+impl Game for RolitGame {
+	fn handle_events(&mut self) {
+		// The events borrow the window, so they are collected before any of them is handled.
+		let events: Vec<Event> = self.window.events().collect();
+		for event in events {
+			if let Event::Closed = event {
+				self.running = false;
+
+				// Let the server know you are leaving, which ends the game for the others.
+				self.nethandler.send(&Packet::AbandonRolit(self.host()));
+				println!("You have abandoned the game.");
+			}
+			else if let Event::MouseButtonPressed {button, x, y} = event {
+				if button == Button::Left {
+					let pos = self.board.piece_index(x as u32, y as u32);
+					match self.board.place(pos, self.colour) {
+						Ok(_) => {
+							self.nethandler.send(&Packet::PlaceRolit(self.host(), pos.0, pos.1));
+							self.check_status();
+						},
+						Err(RolitError::WrongTurn) => println!("It is {}s turn.", COLOUR_NAMES[self.board.turn() as usize]),
+						Err(RolitError::MustFlip) => println!("You have to flip pieces, since you can."),
+						Err(_) => {}
+					}
+				}
+			}
+		}
+	}
+
+	fn handle_packet(&mut self, packet: &Packet) -> bool {
+		match packet {
+			&Packet::PlaceRolit(player, x, y) => {
+				let colour = match self.players.iter().position(|&id| { id == player }) {
+					Some(colour) => colour as Colour,
+					None => return false
+				};
+
+				if let Err(err) = self.board.place((x, y), colour) {
+					println!("[WARNING] {} has made an illegal move at {}. {:?}", COLOUR_NAMES[colour as usize], Square::from((x, y)), err);
+				}
+				self.check_status();
+				true
+			},
+			&Packet::AbandonRolit(player) => {
+				if !self.players.contains(&player) {
+					return false;
+				}
+
+				println!("Client [{}] has left the game of Rolit.", player);
+				self.running = false;
+				true
+			},
+			_ => false
+		}
+	}
+
+	fn running(&self) -> bool {
+		self.running
+	}
+
+	fn draw(&mut self) {
+		self.board.update();
+
 		self.window.clear(&Color::rgb(100, 200, 100));
 		self.window.draw(&self.board);
+		self.window.display();
+	}
+}
+
+/// Runs an engine on its own thread, so that the window stays responsive while it is thinking.
+/// The thread stops as soon as this is dropped and the engine has finished its current search.
+struct EngineThread {
+	boards: Sender<Board>,
+	moves: Receiver<Option<(u8, u8)>>,
+	// Set while the engine is thinking about the last board that has been sent.
+	thinking: bool
+}
+
+impl EngineThread {
+	fn start<E: Engine + 'static>(mut engine: E) -> EngineThread {
+		let (board_sender, board_receiver) = mpsc::channel::<Board>();
+		let (move_sender, move_receiver) = mpsc::channel();
+
+		thread::spawn(move || {
+			for board in board_receiver {
+				if move_sender.send(engine.choose_move(&board)).is_err() {
+					break;
+				}
+			}
+		});
+
+		EngineThread {
+			boards: board_sender,
+			moves: move_receiver,
+			thinking: false
+		}
+	}
+
+	/// Let the engine start thinking about the board, unless it is still busy with another one.
+	fn think(&mut self, board: &Board) {
+		if !self.thinking {
+			self.thinking = self.boards.send(board.clone()).is_ok();
+		}
+	}
+
+	/// Returns the move the engine has decided on, if it is done thinking.
+	fn poll(&mut self) -> Option<Option<(u8, u8)>> {
+		if !self.thinking {
+			return None;
+		}
+
+		match self.moves.try_recv() {
+			Ok(m) => {
+				self.thinking = false;
+				Some(m)
+			},
+			Err(_) => None
+		}
+	}
+}
+
+/// The node budget of the engine for the level, which quadruples with every level.
+pub fn level_limit(level: u8) -> Limit {
+	Limit::Nodes(1000 << (2 * (level as u64 - 1)))
+}
+
+pub const MAX_LEVEL: u8 = 8;
+
+/// Load the opening book of the configuration, if there is one.
+fn load_book() -> Option<Book> {
+	let path = match CONFIG.computer.book {
+		Some(ref path) => path,
+		None => return None
+	};
+
+	match Book::load(path) {
+		Ok(book) => Some(book),
+		Err(err) => {
+			println!("[WARNING] Could not load the opening book {}. {:?}", path, err);
+			None
+		}
+	}
+}
+
+/// The kinds of engines the computer can play with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineKind {
+	/// Alpha-beta search, which looks a number of moves ahead.
+	Search,
+	/// Monte Carlo tree search, which plays many random games to the end.
+	MonteCarlo
+}
+
+/// The number of games the Monte Carlo search plays out for every move at the level, which
+/// doubles with every level.
+pub fn level_playouts(level: u8) -> u64 {
+	500 << (level as u64 - 1)
+}
+
+/// The Monte Carlo search should never keep the player waiting for long, even on slow machines.
+const MCTS_TIME_LIMIT: u64 = 10;
+const MCTS_THREADS: usize = 4;
 
-		// Create the Score Bar
-		let score_size = Rect::<u32> {
-			left: 0,
-			top: self.board.size(),
-			width: self.board.size(),
-			height: SCORE_HEIGHT
+/// A local game against the computer. The player places their pieces by clicking, the moves of
+/// the computer are made on another thread. The engines only know how to win by having the most
+/// pieces, so misère cannot be played against them.
+pub struct AiGame {
+	piece: Piece,
+	engine: EngineThread,
+	board: DrawableBoard,
+	window: RenderWindow,
+	running: bool
+}
+
+impl AiGame {
+	/// Start a game from the board provided, where the player plays with the piece provided. The
+	/// level of the computer goes from 1 to MAX_LEVEL.
+	pub fn new(piece: Piece, level: u8, kind: EngineKind, board: Board) -> AiGame {
+		assert!(level >= 1 && level <= MAX_LEVEL);
+		let (board, window) = initialise_graphics(board, Objective::MostPieces);
+
+		let engine = match kind {
+			EngineKind::Search => {
+				let search = AlphaBeta::new(Positional::new(), level_limit(level)).with_endgame(EndgameSolver::new(8 + level / 2));
+				match load_book() {
+					Some(book) => EngineThread::start(BookEngine::new(book, search)),
+					None => EngineThread::start(search)
+				}
+			},
+			// The Monte Carlo search plays without the book, so that it offers some different
+			// openings as well.
+			EngineKind::MonteCarlo => EngineThread::start(Mcts::new(level_playouts(level)).with_threads(MCTS_THREADS).with_time_limit(Duration::from_secs(MCTS_TIME_LIMIT)))
 		};
-		let score = DrawableScore::new(Score::score(&self.board), score_size);
-		self.window.draw(&score);
 
-		self.window.display();
+		AiGame {
+			piece: piece,
+			engine: engine,
+			board: board,
+			window: window,
+			running: true
+		}
+	}
+
+	/// Make the move on the board for whoever's turn it is and check if the game is over.
+	fn make_move(&mut self, pos: (u8, u8)) -> bool {
+		let turn = self.board.turn();
+		if self.board.place(pos, turn).is_err() {
+			return false;
+		}
+
+		announce_pass(&self.board);
+
+		if announce_result_for(&self.board, self.piece, Objective::MostPieces) {
+			self.running = false;
+		}
+
+		true
+	}
+}
+
+impl Game for AiGame {
+	fn handle_events(&mut self) {
+		// The events borrow the window, so they are collected before any of them is handled.
+		let events: Vec<Event> = self.window.events().collect();
+		for event in events {
+			if let Event::Closed = event {
+				self.running = false;
+			}
+			else if let Event::MouseButtonPressed {button, x, y} = event {
+				if button == Button::Left && self.board.turn() == self.piece {
+					let pos = self.board.piece_index(x as u32, y as u32);
+					self.make_move(pos);
+				}
+			}
+		}
+
+		if !self.running || self.board.turn() == self.piece {
+			return;
+		}
+
+		// It is the computers turn. Check if it has already decided, otherwise let it think.
+		match self.engine.poll() {
+			Some(Some(pos)) => {
+				println!("The computer places at {}.", Square::from(pos));
+				if !self.make_move(pos) {
+					// The computer would only try the same move again, so the game cannot go on.
+					println!("[WARNING] The computer has tried to make an illegal move. Ending the game.");
+					self.running = false;
+				}
+			},
+			Some(None) => {
+				println!("[WARNING] The computer could not find a move. Ending the game.");
+				self.running = false;
+			},
+			None => self.engine.think(&self.board)
+		}
+	}
+
+	fn handle_packet(&mut self,  _: &Packet) -> bool { false }
+
+	fn running(&self) -> bool {
+		self.running
+	}
+
+	fn draw(&mut self) {
+		draw_game(&mut self.window, &mut self.board, Objective::MostPieces);
 	}
 }
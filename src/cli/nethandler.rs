@@ -2,6 +2,7 @@ use std::sync::{Arc, Weak, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::{self, JoinHandle};
 use packets::*;
+use protocol::*;
 use remote::*;
 use std::net::{TcpStream, ToSocketAddrs};
 use std::collections::VecDeque;
@@ -14,6 +15,9 @@ pub type ArcRw<T> = Arc<RwLock<T>>;
 #[derive(Debug)]
 pub enum Error {
 	Refused,
+	SendHelloFailed,
+	/// The server cannot talk to this client, for the reason provided.
+	Incompatible(String),
 	SendLoginFailed,
 	LoginDeny(String),
 	ProtocolError,
@@ -24,6 +28,7 @@ pub enum Error {
 pub struct NetHandler {
 	client_id: ClientId,
 	login_name: String,
+	server_capabilities: Capabilities,
 	remote: Arc<Remote>,
 	packets: ArcRw<Vec<Weak<Mutex<VecDeque<Packet>>>>>,
 	handle: Option<JoinHandle<()>>,
@@ -43,17 +48,30 @@ impl NetHandler {
 			Err(err) => return Err(Error::SockErr(err))
 		};
 
-		// The connection has been established. Let's wait if the server responds with a login
-		// accept.
-		let id = match remote.read_packet() {
-			Ok(Packet::ConnectSuccess(id)) => id,
+		// The connection has been established. Introduce the client to the server and wait if the
+		// server is willing to talk to it.
+		if !remote.write_packet(&Packet::Hello(Handshake::new())) {
+			return Err(Error::SendHelloFailed);
+		}
+
+		let (id, server) = match remote.read_packet() {
+			Ok(Packet::Welcome(id, server)) => (id, server),
+			Ok(Packet::Incompatible(reason)) => return Err(Error::Incompatible(reason)),
 			Ok(p) => {
-				println!("Received unexpected packet {:?}, expected Packet::ConnectSuccess", p);
+				println!("Received unexpected packet {:?}, expected Packet::Welcome", p);
 				return Err(Error::ProtocolError);
 			},
 			Err(err) => return Err(Error::PacketRead(err))
 		};
 
+		// The server should not have accepted the client otherwise, but the client has the final
+		// say on what it can talk to.
+		match Handshake::new().check(&server) {
+			Ok(version) => println!("Connected with protocol version {}. The server supports {}.", version, server.capabilities),
+			Err(Incompatibility::PeerOutdated(needed)) => return Err(Error::Incompatible(format!("The server speaks protocol version {}, but the client needs at least version {}.", server.version, needed))),
+			Err(Incompatibility::Outdated(needed)) => return Err(Error::Incompatible(format!("The client speaks protocol version {}, but the server needs at least version {}. Please update the client.", PROTOCOL_VERSION, needed)))
+		}
+
 		// The connection has been established. Now try to login with the provided
 		// Login name.
 		if !remote.write_packet(&Packet::Login(login_name.to_string())) {
@@ -127,6 +145,7 @@ impl NetHandler {
 		Ok(Arc::new(NetHandler {
 			client_id: id,
 			login_name: login_name.to_string(),
+			server_capabilities: server.capabilities,
 			remote: remote,
 			packets: packets,
 			handle: Some(handle),
@@ -155,6 +174,11 @@ impl NetHandler {
 		self.login_name.clone()
 	}
 
+	/// The optional features the server has announced when the connection was established.
+	pub fn server_capabilities(&self) -> Capabilities {
+		self.server_capabilities
+	}
+
 	/// Returns true if the NetHandler is still connected to the server, otherwise false.
 	pub fn connected(&self) -> bool {
 		self.running.load(Ordering::Relaxed)
@@ -7,14 +7,26 @@ extern crate lazy_static;
 extern crate sfml;
 extern crate toml;
 
+pub mod ai;
+pub mod analysis;
 pub mod board;
 pub mod cli;
+pub mod events;
+pub mod handicap;
+pub mod layout;
 pub mod packets;
+pub mod protocol;
 pub mod remote;
+pub mod rng;
+pub mod rolit;
 pub mod score;
+pub mod square;
+pub mod start;
+pub mod symmetry;
+pub mod transcript;
 
 use std::sync::{Arc, Mutex};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use cli::*;
 use packets::*;
@@ -27,7 +39,9 @@ fn main() {
 		nethandler: None,
 		client_list: Vec::new(),
 		games: Vec::new(),
-		packets: Arc::new(Mutex::new(VecDeque::new()))
+		packets: Arc::new(Mutex::new(VecDeque::new())),
+		requests: HashMap::new(),
+		rolit_requests: Vec::new()
 	};
 
 	let console = Console::new("exit");
@@ -55,9 +69,23 @@ fn main() {
 				// The packet is of a different kind.
 				match packet {
 					Packet::ClientList(clients) => ctx.client_list = clients,
-					Packet::RequestGame(client) => println!("Client [{}] has requested a game. Use challenge to accept the request.", client),
+					Packet::RequestGame(client, options) => {
+						println!("Client [{}] has requested a game on a {}. Use challenge to accept the request.", client, options);
+						ctx.requests.insert(client, options);
+					},
+					Packet::RequestRolit(players) => {
+						println!("Client [{}] has invited you to a game of Rolit with {:?}. Use rolit to accept the invitation.", players[0], players);
+						ctx.rolit_requests.push(players);
+					},
+					Packet::StartRolit(players) => {
+						ctx.rolit_requests.retain(|requested| { *requested != players });
+						ctx.games.push(Box::new(RolitGame::new(ctx.nethandler.as_ref().unwrap().clone(), players)));
+					},
 					Packet::Message(client, message) => println!("[{}]: {}", client, message),
-					Packet::StartGame(opponent, piece) => ctx.games.push(Box::new(OnlineGame::new(ctx.nethandler.as_ref().unwrap().clone(), piece, opponent))),
+					Packet::StartGame(opponent, piece, options) => {
+						ctx.requests.remove(&opponent);
+						ctx.games.push(Box::new(OnlineGame::new(ctx.nethandler.as_ref().unwrap().clone(), piece, opponent, options)));
+					},
 					p => println!("{:?} was not handled.", p)
 				}
 			}
@@ -0,0 +1,195 @@
+//! Lets others follow what happens on a board. Instead of comparing the board with the last state
+//! they have seen, observers are told about every change as it happens.
+use std::ops::Deref;
+use std::sync::mpsc::{self, Receiver, Sender};
+use board::{Board, Move, MoveOutcome, Piece, PlaceError};
+use score::{GameResult, Objective, Score, Scoring};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BoardEvent {
+	/// The piece has been placed on the square.
+	Placed { piece: Piece, square: (u8, u8) },
+	/// The pieces on the squares have been flipped and now have the colour provided.
+	Flipped { piece: Piece, squares: Vec<(u8, u8)> },
+	/// The player with the piece has passed.
+	Passed(Piece),
+	/// The move has been taken back.
+	Undone(Move),
+	/// The move has been made again after it had been undone.
+	Redone(Move),
+	/// Neither player can place a piece any more. The winner is decided by the objective of the
+	/// board.
+	GameOver(GameResult)
+}
+
+/// Anything that wants to be told about the changes on a board.
+pub trait Observer: Send {
+	/// Called for every event. Returns false if the observer is no longer interested, in which
+	/// case it is removed.
+	fn notify(&mut self, event: &BoardEvent) -> bool;
+}
+
+/// Passes the events on through the channel, until the receiver has been dropped.
+impl Observer for Sender<BoardEvent> {
+	fn notify(&mut self, event: &BoardEvent) -> bool {
+		self.send(event.clone()).is_ok()
+	}
+}
+
+/// A board that tells its observers about every move. It can be read like a normal board, but
+/// can only be changed through its own methods, so no change goes unnoticed.
+pub struct ObservedBoard {
+	board: Board,
+	objective: Objective,
+	scoring: Scoring,
+	observers: Vec<Box<Observer>>
+}
+
+impl ObservedBoard {
+	pub fn new(board: Board) -> ObservedBoard {
+		ObservedBoard {
+			board: board,
+			objective: Objective::MostPieces,
+			scoring: Scoring::Pieces,
+			observers: Vec::new()
+		}
+	}
+
+	/// Decide the winner of the game by the objective, so misère games report the right one.
+	pub fn with_objective(mut self, objective: Objective) -> ObservedBoard {
+		self.objective = objective;
+		self
+	}
+
+	/// The objective the winner is decided by.
+	pub fn objective(&self) -> Objective {
+		self.objective
+	}
+
+	/// Count the pieces of the final result with the scoring, like the rest of the program does.
+	pub fn with_scoring(mut self, scoring: Scoring) -> ObservedBoard {
+		self.scoring = scoring;
+		self
+	}
+
+	/// The scoring the final result is counted with.
+	pub fn scoring(&self) -> Scoring {
+		self.scoring
+	}
+
+	/// Register the observer, which will be told about all changes from now on.
+	pub fn subscribe(&mut self, observer: Box<Observer>) {
+		self.observers.push(observer);
+	}
+
+	/// Create a channel that receives all events from now on.
+	pub fn events(&mut self) -> Receiver<BoardEvent> {
+		let (sender, receiver) = mpsc::channel();
+		self.subscribe(Box::new(sender));
+		receiver
+	}
+
+	fn emit(&mut self, event: BoardEvent) {
+		let mut i = 0;
+		while i < self.observers.len() {
+			if self.observers[i].notify(&event) {
+				i += 1;
+			}
+			else {
+				self.observers.remove(i);
+			}
+		}
+	}
+
+	/// Tell the observers if the game has ended.
+	fn emit_game_over(&mut self) {
+		let result = Score::with_scoring(&self.board, self.scoring).with_objective(self.objective).result();
+		if let Some(result) = result {
+			self.emit(BoardEvent::GameOver(result));
+		}
+	}
+
+	/// Place the piece like Board::place does.
+	pub fn place(&mut self, square: (u8, u8), piece: Piece) -> Result<MoveOutcome, PlaceError> {
+		let outcome = self.board.place(square, piece)?;
+
+		self.emit(BoardEvent::Placed { piece: piece, square: square });
+		self.emit(BoardEvent::Flipped { piece: piece, squares: outcome.flipped() });
+		if outcome.opponent_passed {
+			self.emit(BoardEvent::Passed(piece.opposite()));
+		}
+		self.emit_game_over();
+
+		Ok(outcome)
+	}
+
+	/// Pass like Board::pass does.
+	pub fn pass(&mut self) -> bool {
+		let turn = self.board.turn();
+		if !self.board.pass() {
+			return false;
+		}
+
+		self.emit(BoardEvent::Passed(turn));
+		self.emit_game_over();
+		true
+	}
+
+	/// Take back the last move like Board::undo does.
+	pub fn undo(&mut self) -> Option<Move> {
+		let m = self.board.undo();
+		if let Some(m) = m {
+			self.emit(BoardEvent::Undone(m));
+		}
+
+		m
+	}
+
+	/// Make the last move that has been undone again like Board::redo does.
+	pub fn redo(&mut self) -> Option<Move> {
+		let m = self.board.redo();
+		if let Some(m) = m {
+			self.emit(BoardEvent::Redone(m));
+			self.emit_game_over();
+		}
+
+		m
+	}
+}
+
+impl Deref for ObservedBoard {
+	type Target = Board;
+
+	fn deref(&self) -> &Board {
+		&self.board
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn misere_game_over() {
+		// Black takes the only white piece, which ends the game with black having all pieces.
+		let board: Board = "BW-------------- B".parse().unwrap();
+		let mut board = ObservedBoard::new(board).with_objective(Objective::FewestPieces);
+		let events = board.events();
+
+		board.place((2, 0), Piece::Black).unwrap();
+		let last = events.try_iter().last();
+		assert_eq!(last, Some(BoardEvent::GameOver(GameResult::WhiteWins { black: 3, white: 0 })));
+	}
+
+	#[test]
+	fn game_over_uses_scoring() {
+		// The same game, but the empty squares are counted for black, who has lost.
+		let board: Board = "BW-------------- B".parse().unwrap();
+		let mut board = ObservedBoard::new(board).with_objective(Objective::FewestPieces).with_scoring(Scoring::EmptiesToWinner);
+		let events = board.events();
+
+		board.place((2, 0), Piece::Black).unwrap();
+		let last = events.try_iter().last();
+		assert_eq!(last, Some(BoardEvent::GameOver(GameResult::WhiteWins { black: 16, white: 0 })));
+	}
+}
@@ -0,0 +1,63 @@
+//! Handicaps let players of different strength have an even game. The weaker player starts with
+//! some of the corners already taken, which can never be flipped.
+use std::fmt;
+use board::{Bitboard, Board, Piece, square_bit};
+
+/// The most corners a handicap can give.
+pub const MAX_CORNERS: u8 = 4;
+
+/// The corner pieces one player gets before the game starts.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct Handicap {
+	/// The player who gets the corners, usually the weaker one.
+	pub piece: Piece,
+	/// The number of corners, from 1 to MAX_CORNERS.
+	pub corners: u8
+}
+
+impl Handicap {
+	pub fn new(piece: Piece, corners: u8) -> Handicap {
+		Handicap {
+			piece: piece,
+			corners: corners
+		}
+	}
+
+	pub fn valid(&self) -> bool {
+		self.corners >= 1 && self.corners <= MAX_CORNERS
+	}
+
+	/// The corners of a board with the side length provided that are given. Opposite corners are
+	/// handed out first.
+	pub fn squares(&self, size: u8) -> Vec<(u8, u8)> {
+		let last = size - 1;
+		let corners = [(0, 0), (last, last), (last, 0), (0, last)];
+		corners[..self.corners as usize].to_vec()
+	}
+
+	/// The corners as a bitboard.
+	pub fn bits(&self, size: u8) -> Bitboard {
+		self.squares(size).into_iter().fold(0, |bits, square| { bits | square_bit(square) })
+	}
+
+	/// Place the pieces of the handicap on the board.
+	pub fn apply(&self, board: &mut Board) {
+		for square in self.squares(board.size()) {
+			board.set_square(square, Some(self.piece));
+		}
+	}
+}
+
+impl fmt::Display for Handicap {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let player = match self.piece {
+			Piece::Black => "black",
+			Piece::White => "white"
+		};
+
+		match self.corners {
+			1 => write!(f, "1 corner for {}", player),
+			n => write!(f, "{} corners for {}", n, player)
+		}
+	}
+}
@@ -0,0 +1,122 @@
+//! Layouts of blocked squares, on which no piece can ever be placed. They break up the lines of the
+//! board, so the usual openings and patterns no longer work.
+use std::fmt;
+use board::{Bitboard, Board, Piece, Status, square_bit};
+use rng::Rng;
+use square::Square;
+
+/// The squares that are blocked in a game.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub enum Layout {
+	/// No square is blocked.
+	Open,
+	/// The number of squares provided is blocked at random when the game is created.
+	Random(u8),
+	/// Exactly the squares provided are blocked.
+	Squares(Vec<(u8, u8)>)
+}
+
+/// The start pieces in the centre of a board with the side length provided, which may not be
+/// blocked.
+fn centre(size: u8) -> Bitboard {
+	let c = size / 2;
+	square_bit((c - 1, c - 1)) | square_bit((c, c - 1)) | square_bit((c - 1, c)) | square_bit((c, c))
+}
+
+/// The most squares that may be blocked on a board with the side length provided, which is a
+/// quarter of them.
+pub fn max_blocked(size: u8) -> usize {
+	size as usize * size as usize / 4
+}
+
+impl Layout {
+	/// The blocked squares as a bitboard, or None for a random layout, whose squares have not been
+	/// chosen yet.
+	pub fn blocked(&self) -> Option<Bitboard> {
+		match self {
+			&Layout::Open => Some(0),
+			&Layout::Random(_) => None,
+			&Layout::Squares(ref squares) => Some(squares.iter().fold(0, |blocked, &square| { blocked | square_bit(square) }))
+		}
+	}
+
+	/// Check if a game can be played on a board of the size with this layout. Only a quarter of the
+	/// squares may be blocked and never the ones of the start pieces. The chosen squares must also
+	/// leave black a move to start with.
+	pub fn valid(&self, size: u8) -> bool {
+		match self {
+			&Layout::Open => true,
+			&Layout::Random(count) => count as usize <= max_blocked(size),
+			&Layout::Squares(ref squares) => {
+				if squares.len() > max_blocked(size) || squares.iter().any(|&(x, y)| { x >= size || y >= size }) {
+					return false;
+				}
+
+				let blocked = self.blocked().unwrap_or(0);
+				blocked & centre(size) == 0 && Board::with_size(size).with_blocked(blocked).status() == Status::InProgress { to_move: Piece::Black }
+			}
+		}
+	}
+
+	/// Choose the squares of a random layout, so the same squares can be used by everyone playing
+	/// the game. None of the excluded squares are chosen, like the corners of a handicap. Other
+	/// layouts stay as they are. The layout must be valid for the size.
+	pub fn resolve(&self, size: u8, excluded: Bitboard, rng: &mut Rng) -> Layout {
+		let count = match self {
+			&Layout::Random(count) => count as usize,
+			layout => return layout.clone()
+		};
+
+		// Block random squares until the start position can be played.
+		loop {
+			let mut free: Vec<(u8, u8)> = (0..size).flat_map(|y| { (0..size).map(move |x| { (x, y) }) })
+				.filter(|&square| { square_bit(square) & (centre(size) | excluded) == 0 })
+				.collect();
+
+			let mut squares = Vec::with_capacity(count);
+			for _ in 0..count {
+				let i = rng.below(free.len() as u64) as usize;
+				squares.push(free.swap_remove(i));
+			}
+
+			squares.sort();
+			let layout = Layout::Squares(squares);
+			if layout.valid(size) {
+				return layout;
+			}
+		}
+	}
+}
+
+impl fmt::Display for Layout {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			&Layout::Open => write!(f, "no blocked squares"),
+			&Layout::Random(count) => write!(f, "{} random blocked squares", count),
+			&Layout::Squares(ref squares) => {
+				let names: Vec<String> = squares.iter().map(|&square| { Square::from(square).to_string() }).collect();
+				write!(f, "the blocked squares {}", names.join(", "))
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolve_keeps_excluded_squares_free() {
+		// All four corners of the smallest board are excluded, which leaves eight squares to
+		// choose the four blocked ones from.
+		let corners = square_bit((0, 0)) | square_bit((3, 0)) | square_bit((0, 3)) | square_bit((3, 3));
+		let mut rng = Rng::new(1);
+		for _ in 0..100 {
+			let layout = Layout::Random(4).resolve(4, corners, &mut rng);
+			let blocked = layout.blocked().unwrap();
+			assert_eq!(blocked.count_ones(), 4);
+			assert_eq!(blocked & corners, 0);
+			assert!(layout.valid(4));
+		}
+	}
+}
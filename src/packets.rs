@@ -1,15 +1,28 @@
-use bincode::{serialize, deserialize, Bounded, Error};
+use bincode::{serialize, deserialize, Infinite, Error};
 
-use std::net::TcpStream;
 use std::io::{Read, Write};
 use std::io;
+use std::fmt;
 
-use board::Piece;
+use board::{Bitboard, Board, Piece, DEFAULT_SIZE, valid_size};
+use score::Objective;
+use handicap::Handicap;
+use layout::Layout;
+use protocol::Handshake;
+use rng::Rng;
+use start::Start;
 
 pub type ClientId = u64;
 pub use std::u64::MAX as ClientIdMAX;
 
-pub const MAX_PACKET_SIZE: u64 = 512;
+/// The largest packet that may be sent, not counting the length in front of it.
+pub const MAX_PACKET_SIZE: u64 = 16384;
+
+/// The number of bytes of the length every packet is preceded by.
+pub const HEADER_SIZE: usize = 4;
+
+// The number of bytes that are read from the stream at once.
+const READ_CHUNK_SIZE: usize = 512;
 
 #[derive(Debug)]
 pub enum PacketReadError {
@@ -17,15 +30,164 @@ pub enum PacketReadError {
 	DeserializeError(Error),
 	/// The packet could not be read properly from the stream.
 	IOError(io::Error),
+	/// The peer has sent a frame of the length provided, which is larger than MAX_PACKET_SIZE.
+	TooLarge(u64),
 	/// The connection has been closed by the peer socket.
 	Closed
 }
 
+#[derive(Debug)]
+pub enum PacketWriteError {
+	/// The packet could not be serialised.
+	SerializeError(Error),
+	/// The packet would have the length provided, which is larger than MAX_PACKET_SIZE.
+	TooLarge(u64),
+	/// The packet could not be written to the stream completely.
+	IOError(io::Error)
+}
+
+/// The rules of a game, which both players have to agree on before it is started.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct GameOptions {
+	/// The side length of the board.
+	pub size: u8,
+	/// Decides who wins, so misère games can be played.
+	pub objective: Objective,
+	/// The squares no piece can be placed on.
+	pub layout: Layout,
+	/// The position the game starts from.
+	pub start: Start,
+	/// The corners one of the players gets before the game starts.
+	pub handicap: Option<Handicap>
+}
+
+impl GameOptions {
+	/// The options of a standard game.
+	pub fn new() -> GameOptions {
+		GameOptions {
+			size: DEFAULT_SIZE,
+			objective: Objective::MostPieces,
+			layout: Layout::Open,
+			start: Start::Standard,
+			handicap: None
+		}
+	}
+
+	/// Play on a board with the side length provided.
+	pub fn with_size(mut self, size: u8) -> GameOptions {
+		self.size = size;
+		self
+	}
+
+	/// Play with the objective provided.
+	pub fn with_objective(mut self, objective: Objective) -> GameOptions {
+		self.objective = objective;
+		self
+	}
+
+	/// Block the squares of the layout provided.
+	pub fn with_layout(mut self, layout: Layout) -> GameOptions {
+		self.layout = layout;
+		self
+	}
+
+	/// Start from the position provided.
+	pub fn with_start(mut self, start: Start) -> GameOptions {
+		self.start = start;
+		self
+	}
+
+	/// Give the corners of the handicap to one of the players.
+	pub fn with_handicap(mut self, handicap: Handicap) -> GameOptions {
+		self.handicap = Some(handicap);
+		self
+	}
+
+	/// The corners of the handicap, which may not be blocked.
+	fn handicap_bits(&self) -> Bitboard {
+		self.handicap.map_or(0, |handicap| { handicap.bits(self.size) })
+	}
+
+	/// The board before the start position has been played, with the blocked squares and the
+	/// pieces of the handicap.
+	fn empty_board(&self, blocked: Bitboard) -> Board {
+		let mut board = Board::with_size(self.size).with_blocked(blocked);
+		if let Some(handicap) = self.handicap {
+			handicap.apply(&mut board);
+		}
+
+		board
+	}
+
+	/// Check if a game can be played with these options. As long as the blocked squares are
+	/// random, the start position can only be random as well.
+	pub fn valid(&self) -> bool {
+		if !valid_size(self.size) || !self.layout.valid(self.size) || !self.handicap.map_or(true, |handicap| { handicap.valid() }) {
+			return false;
+		}
+
+		match self.layout.blocked() {
+			Some(blocked) => blocked & self.handicap_bits() == 0 && self.start.valid(&self.empty_board(blocked)),
+			None => match self.start {
+				Start::Standard | Start::Random { .. } => self.start.valid(&self.empty_board(0)),
+				_ => false
+			}
+		}
+	}
+
+	/// The same options, but with the blocked squares and the start position chosen in case they
+	/// are random, so they can be sent to both players.
+	pub fn resolved(&self, rng: &mut Rng) -> GameOptions {
+		// The corners of the handicap have to stay free.
+		let layout = self.layout.resolve(self.size, self.handicap_bits(), rng);
+
+		let start = self.start.resolve(&self.empty_board(layout.blocked().unwrap()), rng);
+		self.clone().with_layout(layout).with_start(start)
+	}
+
+	/// Create the board the game starts with. The options have to be resolved first.
+	pub fn board(&self) -> Board {
+		let blocked = self.layout.blocked().expect("The layout has to be resolved before the board is created.");
+		let mut board = self.empty_board(blocked);
+		assert!(self.start.play(&mut board), "The start position has to be resolved before the board is created.");
+		board
+	}
+}
+
+impl fmt::Display for GameOptions {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}x{} board", self.size, self.size)?;
+		if self.objective == Objective::FewestPieces {
+			write!(f, " with misère rules")?;
+		}
+		if self.layout != Layout::Open {
+			write!(f, " and {}", self.layout)?;
+		}
+		if self.start != Start::Standard {
+			write!(f, ", starting from {}", self.start)?;
+		}
+		if let Some(handicap) = self.handicap {
+			write!(f, ", with a handicap of {}", handicap)?;
+		}
+
+		Ok(())
+	}
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub enum Packet {
-	/// Packet to let the client know which id it will have. This is the first packet sent to the
-	/// client by the server when the connection has been established.
-	ConnectSuccess(ClientId),
+	// The first three packets make up the handshake. They must keep their place and their form in
+	// every version of the protocol, so that any two programs can at least tell why they cannot
+	// talk to each other.
+	/// The first packet sent by the client when the connection has been established, announcing
+	/// its protocol version and capabilities (Client->Server only).
+	Hello(Handshake),
+	/// The answer to a Hello when the server accepts the client. Contains the id of the client and
+	/// the handshake of the server (Server->Client only).
+	Welcome(ClientId, Handshake),
+	/// The answer to a Hello when the server cannot talk to the client, with the reason. The
+	/// connection is closed afterwards (Server->Client only).
+	Incompatible(String),
 	/// This packet is never actually sent over the network, but it is used internally to handle
 	/// disconnects a little more gracefully and easily.
 	Disconnect,
@@ -43,21 +205,25 @@ pub enum Packet {
 	/// Request a game. On the server, ClientId is the id of the requestee, on the client the id
 	/// of the one who has requested.
 	/// In direction Client->Server it is also the package used to accept a request, simply by
-	/// making a request to the client that had requested the game in the first place.
-	RequestGame(ClientId),
+	/// making a request to the client that had requested the game in the first place with the same
+	/// options. Different options count as a new request to the other client.
+	RequestGame(ClientId, GameOptions),
 	/// Deny a game response. If there was no request registered in the first place, this will
 	/// probably do nothing at all.
 	/// In direction Server->Client the id is of the one who has denied.
 	/// In direction Client->Server it is the id of the one to deny the game of.
 	DenyGame(ClientId),
 	/// Start a game with a fresh board. This is Server->Client only and the colour the client will
-	/// be controlling is sent, as well as the id of the opponent.
-	StartGame(ClientId, Piece),
+	/// be controlling is sent, as well as the id of the opponent and the options both have agreed
+	/// on. The server has chosen the blocked squares and the start position, if they were random.
+	StartGame(ClientId, Piece, GameOptions),
 	/// Place a piece onto the board of the client with the ClientId, which is always the id of the
 	/// opponent. The server will never respond to this packet, since the client is supposed to
 	/// check the rules themselves aswell.
 	PlacePiece(ClientId, u8, u8),
-	/// When ingame with another player, this packet can be sent by the player whos turn it is.
+	/// When ingame with another player, this packet can be sent by the player whos turn it is, if
+	/// they cannot place a piece anywhere. Since the board passes automatically, this is usually
+	/// not necessary.
 	/// Server->Client the id of the one passing is provided
 	/// Client->Server the id of the one the client is currently playing with is.
 	Pass(ClientId),
@@ -65,6 +231,22 @@ pub enum Packet {
 	/// game, Client->Server it is the one the client is abandoning the game with.
 	/// The first client sending this packet will be considered to have lost the game.
 	AbandonGame(ClientId),
+	/// Invite other clients to a game of Rolit. The ids are those of all players in turn order,
+	/// starting with the one who has invited the others, which identifies the game from then on.
+	/// The server passes the invitation on to everyone else on the list. It is accepted by sending
+	/// the same list back, a DenyGame to the one who has invited declines it.
+	RequestRolit(Vec<ClientId>),
+	/// Start a game of Rolit once all players have accepted (Server->Client only). The ids are
+	/// those of all players in turn order, the colour of a client is its place in the list.
+	StartRolit(Vec<ClientId>),
+	/// Place a piece in a game of Rolit. In direction Client->Server the id is the one of the
+	/// player who has started the game, Server->Client it is the id of the player who has placed
+	/// the piece.
+	PlaceRolit(ClientId, u8, u8),
+	/// Leave a game of Rolit, which ends it for everyone. The id is the one of the player who has
+	/// started the game in direction Client->Server and the one of the leaving player in direction
+	/// Server->Client.
+	AbandonRolit(ClientId),
 	/// Message to or from another client. If it is in direction Server->Client, the ID of the client
 	/// that has sent the message is the id, in direction Client->Server it's the id of the client
 	/// it is directed at.
@@ -72,50 +254,243 @@ pub enum Packet {
 }
 
 impl Packet {
-	pub fn write_to_stream(&self, stream: &mut TcpStream) -> bool {
-		let size = Bounded(MAX_PACKET_SIZE);
-
-		let data: Vec<u8> = match serialize(&self, size) {
+	/// Write the packet as one frame, its length followed by its data. Short writes are continued
+	/// until everything has been written.
+	pub fn write_to_stream<W: Write>(&self, stream: &mut W) -> Result<(), PacketWriteError> {
+		let data: Vec<u8> = match serialize(&self, Infinite) {
 			Ok(data) => data,
-			Err(err) => { println!("Error serialising packet: {}", err); return false; }
+			Err(err) => return Err(PacketWriteError::SerializeError(err))
 		};
 
-		match stream.write(&data) {
-			Ok(len) => {
-				assert!(len <= MAX_PACKET_SIZE as usize);
-				true
-			},
-			Err(err) => {
-				println!("Failed writing packet to stream: {}", err);
-				false
+		if data.len() as u64 > MAX_PACKET_SIZE {
+			return Err(PacketWriteError::TooLarge(data.len() as u64));
+		}
+
+		let mut frame = Vec::with_capacity(HEADER_SIZE + data.len());
+		frame.extend_from_slice(&encode_length(data.len() as u32));
+		frame.extend_from_slice(&data);
+
+		match stream.write_all(&frame) {
+			Ok(()) => Ok(()),
+			Err(err) => Err(PacketWriteError::IOError(err))
+		}
+	}
+}
+
+/// The length in front of every frame, with the most significant byte first.
+fn encode_length(len: u32) -> [u8; HEADER_SIZE] {
+	[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]
+}
+
+fn decode_length(header: &[u8]) -> u32 {
+	header[..HEADER_SIZE].iter().fold(0, |len, &byte| { len << 8 | byte as u32 })
+}
+
+/// Reads the packets from a stream, which may deliver them in pieces or several at once. All
+/// bytes that have been received but do not make up a complete frame yet are kept for the next
+/// read, so a timeout in the middle of a frame does not lose anything.
+pub struct PacketReader<R: Read> {
+	stream: R,
+	buffer: Vec<u8>,
+	// The number of bytes that still have to be thrown away from a frame that was too large.
+	skip: u64
+}
+
+impl<R: Read> PacketReader<R> {
+	pub fn new(stream: R) -> PacketReader<R> {
+		PacketReader {
+			stream: stream,
+			buffer: Vec::new(),
+			skip: 0
+		}
+	}
+
+	/// The stream the packets are read from.
+	pub fn get_ref(&self) -> &R {
+		&self.stream
+	}
+
+	/// Read the next packet. This blocks until a complete frame has arrived, the stream has been
+	/// closed or its timeout has run out. A frame that is too large is skipped once the error has
+	/// been returned, so the packets after it can still be read.
+	pub fn read_packet(&mut self) -> Result<Packet, PacketReadError> {
+		loop {
+			if let Some(frame) = self.next_frame()? {
+				return match deserialize(&frame) {
+					Ok(p) => Ok(p),
+					Err(err) => Err(PacketReadError::DeserializeError(err))
+				};
+			}
+
+			let mut chunk = [0; READ_CHUNK_SIZE];
+			match self.stream.read(&mut chunk) {
+				Ok(0) => return Err(PacketReadError::Closed),
+				Ok(len) => self.receive(&chunk[..len]),
+				Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+				Err(err) => return Err(PacketReadError::IOError(err))
 			}
 		}
 	}
 
-	// TODO: Later, the buffer could be borrowed, which would increase performance.
-	// This has to be crosschecked with the inner workings of bytecode, however.
-	/// Read a packet from the stream. This returns a packet, in case one could be read
-	/// in conjunction with a bool stating false, in case the stream has been closed.
-	pub fn read_from_stream(stream: &mut TcpStream) -> Result<Packet, PacketReadError> {
-		let mut data: Vec<u8> = vec![0; MAX_PACKET_SIZE as usize];
-
-		match stream.read(&mut data) {
-			Ok(len) => {
-				if len == 0 {
-					Err(PacketReadError::Closed)
-				}
-				else {
-					match deserialize(&data) {
-						Ok(p) => Ok(p),
-						Err(err) => {
-							Err(PacketReadError::DeserializeError(err))
-						}
-					}
-				}
+	/// Add the bytes to the buffer, unless they belong to a frame that is being skipped.
+	fn receive(&mut self, data: &[u8]) {
+		let skipped = (self.skip as usize).min(data.len());
+		self.skip -= skipped as u64;
+		self.buffer.extend_from_slice(&data[skipped..]);
+	}
+
+	/// Take the first complete frame out of the buffer, if it has arrived already.
+	fn next_frame(&mut self) -> Result<Option<Vec<u8>>, PacketReadError> {
+		if self.buffer.len() < HEADER_SIZE {
+			return Ok(None);
+		}
+
+		let len = decode_length(&self.buffer) as u64;
+		if len > MAX_PACKET_SIZE {
+			// Throw away the frame, including what has already been received of it.
+			let received = (self.buffer.len() - HEADER_SIZE) as u64;
+			let dropped = (HEADER_SIZE as u64 + len).min(self.buffer.len() as u64) as usize;
+			self.buffer.drain(..dropped);
+			self.skip = len - received.min(len);
+			return Err(PacketReadError::TooLarge(len));
+		}
+
+		let end = HEADER_SIZE + len as usize;
+		if self.buffer.len() < end {
+			return Ok(None);
+		}
+
+		let frame = self.buffer[HEADER_SIZE..end].to_vec();
+		self.buffer.drain(..end);
+		Ok(Some(frame))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::VecDeque;
+
+	/// A stream that hands out the bytes in the chunks they have been queued in. An empty chunk
+	/// is a timeout, and once all chunks are gone the stream is closed.
+	struct Chunked {
+		chunks: VecDeque<Vec<u8>>
+	}
+
+	impl Chunked {
+		fn new(chunks: Vec<Vec<u8>>) -> Chunked {
+			Chunked { chunks: chunks.into_iter().collect() }
+		}
+	}
+
+	impl Read for Chunked {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let mut chunk = match self.chunks.pop_front() {
+				Some(chunk) => chunk,
+				None => return Ok(0)
+			};
+
+			if chunk.is_empty() {
+				return Err(io::Error::new(io::ErrorKind::WouldBlock, "timed out"));
 			}
-			// XXX: There might be some cleanup to do in case of the following
-			// error, which still needs to be tested.
-			Err (err) => Err(PacketReadError::IOError(err))
+
+			let len = chunk.len().min(buf.len());
+			buf[..len].copy_from_slice(&chunk[..len]);
+			if len < chunk.len() {
+				self.chunks.push_front(chunk.split_off(len));
+			}
+			Ok(len)
+		}
+	}
+
+	/// A stream that accepts at most a few bytes per write.
+	struct Trickle {
+		written: Vec<u8>
+	}
+
+	impl Write for Trickle {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			let len = buf.len().min(3);
+			self.written.extend_from_slice(&buf[..len]);
+			Ok(len)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn frame(packet: &Packet) -> Vec<u8> {
+		let mut written = Vec::new();
+		packet.write_to_stream(&mut written).unwrap();
+		written
+	}
+
+	#[test]
+	fn frame_in_single_bytes() {
+		let packet = Packet::Message(3, "Hello there".to_string());
+		let mut chunks: Vec<Vec<u8>> = frame(&packet).into_iter().map(|byte| { vec![byte] }).collect();
+		// The timeout hits in the middle of the data.
+		chunks.insert(HEADER_SIZE + 2, Vec::new());
+		let mut reader = PacketReader::new(Chunked::new(chunks));
+
+		match reader.read_packet() {
+			Err(PacketReadError::IOError(ref err)) if err.kind() == io::ErrorKind::WouldBlock => {},
+			other => panic!("Expected a timeout, got {:?}", other)
+		}
+		assert_eq!(reader.read_packet().unwrap(), packet);
+		match reader.read_packet() {
+			Err(PacketReadError::Closed) => {},
+			other => panic!("Expected the stream to be closed, got {:?}", other)
+		}
+	}
+
+	#[test]
+	fn two_frames_in_one_read() {
+		let first = Packet::PlaceRolit(1, 2, 3);
+		let second = Packet::Login("someone".to_string());
+		let mut data = frame(&first);
+		data.extend(frame(&second));
+		let mut reader = PacketReader::new(Chunked::new(vec![data]));
+
+		assert_eq!(reader.read_packet().unwrap(), first);
+		assert_eq!(reader.read_packet().unwrap(), second);
+	}
+
+	#[test]
+	fn oversized_frame_is_skipped() {
+		let len = MAX_PACKET_SIZE as u32 + 1;
+		let mut data = encode_length(len).to_vec();
+		data.extend(vec![0; len as usize]);
+		let packet = Packet::RequestClientList;
+		data.extend(frame(&packet));
+
+		// Part of the large frame is still on its way when the error is returned.
+		let mut reader = PacketReader::new(Chunked::new(vec![data[..100].to_vec(), data[100..].to_vec()]));
+		match reader.read_packet() {
+			Err(PacketReadError::TooLarge(l)) => assert_eq!(l, len as u64),
+			other => panic!("Expected the frame to be too large, got {:?}", other)
+		}
+		assert_eq!(reader.read_packet().unwrap(), packet);
+	}
+
+	#[test]
+	fn short_writes_are_continued() {
+		let packet = Packet::Message(7, "A message longer than a single write".to_string());
+		let mut stream = Trickle { written: Vec::new() };
+		packet.write_to_stream(&mut stream).unwrap();
+
+		assert_eq!(stream.written, frame(&packet));
+		let mut reader = PacketReader::new(Chunked::new(vec![stream.written]));
+		assert_eq!(reader.read_packet().unwrap(), packet);
+	}
+
+	#[test]
+	fn oversized_packet_is_not_written() {
+		let packet = Packet::Message(7, "x".repeat(MAX_PACKET_SIZE as usize));
+		match packet.write_to_stream(&mut Vec::new()) {
+			Err(PacketWriteError::TooLarge(_)) => {},
+			other => panic!("Expected the packet to be too large, got {:?}", other)
 		}
 	}
 }
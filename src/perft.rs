@@ -0,0 +1,66 @@
+//! Counts the positions that can be reached from a position in a number of moves, for every move
+//! separately. Comparing the numbers with known ones shows whether the rules are followed.
+//!
+//! Usage: perft <depth> [position]
+//!
+//! The position is written as for the setup command, so it can be on a board of any size, and
+//! defaults to the start position. From the 8x8 start, the numbers for the depths 1 to 9 must be
+//! 4, 12, 56, 244, 1396, 8200, 55092, 390216 and 3005288.
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod board;
+pub mod score;
+pub mod square;
+pub mod transcript;
+
+use std::env;
+use std::process;
+use std::time::Instant;
+
+use board::Board;
+use square::Square;
+
+fn main() {
+	let args: Vec<String> = env::args().collect();
+	if args.len() < 2 {
+		println!("Usage: {} <depth> [position]", args[0]);
+		process::exit(1);
+	}
+
+	let depth: u8 = args[1].parse().unwrap_or_else(|_| {
+		println!("The depth must be a number.");
+		process::exit(1);
+	});
+
+	// The position contains a space before the piece to move, so it may be split in several
+	// arguments.
+	let board = if args.len() > 2 {
+		args[2..].concat().parse().unwrap_or_else(|err| {
+			println!("Could not read position. {:?}", err);
+			process::exit(1);
+		})
+	}
+	else { Board::new() };
+
+	let start = Instant::now();
+	let mut total = 0;
+	for (m, count) in board.divide(depth) {
+		match m {
+			Some(pos) => println!("{}: {}", Square::from(pos), count),
+			None => println!("--: {}", count)
+		}
+
+		total += count;
+	}
+
+	if total == 0 {
+		total = board.perft(depth);
+	}
+
+	let elapsed = start.elapsed();
+	println!("");
+	println!("Positions: {}", total);
+	println!("Time: {}.{:03}s", elapsed.as_secs(), elapsed.subsec_nanos() / 1000000);
+}
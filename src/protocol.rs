@@ -0,0 +1,117 @@
+//! The version of the network protocol and the optional features a program supports. Client and
+//! server exchange them when a connection is established, so a server can be updated while some
+//! of its clients are still running an older version, and the other way around.
+use std::fmt;
+use std::ops::BitOr;
+
+/// The version of the protocol this program speaks. It has to be raised whenever a packet changes
+/// in a way older programs cannot read.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// The oldest version of the protocol this program can still talk to.
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// A set of optional features. A feature may only be used with a peer that has announced it, so
+/// new features can be added without raising the protocol version. Flags that are unknown to this
+/// program are kept, but ignored.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct Capabilities(pub u64);
+
+impl Capabilities {
+	pub const NONE: Capabilities = Capabilities(0);
+	/// Games with options other than the standard ones, like other board sizes, misère rules,
+	/// blocked squares, start positions and handicaps.
+	pub const VARIANTS: Capabilities = Capabilities(1 << 0);
+	/// Games of Rolit for three or four players.
+	pub const ROLIT: Capabilities = Capabilities(1 << 1);
+
+	/// The names of all known flags.
+	const NAMES: [(Capabilities, &'static str); 2] = [
+		(Capabilities::VARIANTS, "variants"),
+		(Capabilities::ROLIT, "rolit")
+	];
+
+	/// Every feature this program supports.
+	pub fn supported() -> Capabilities {
+		Capabilities::VARIANTS | Capabilities::ROLIT
+	}
+
+	/// Check if all flags of the other set are in this one as well.
+	pub fn contains(&self, other: Capabilities) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	/// The flags that are in both sets, which are the features two peers can use with each other.
+	pub fn common(&self, other: Capabilities) -> Capabilities {
+		Capabilities(self.0 & other.0)
+	}
+}
+
+impl BitOr for Capabilities {
+	type Output = Capabilities;
+
+	fn bitor(self, other: Capabilities) -> Capabilities {
+		Capabilities(self.0 | other.0)
+	}
+}
+
+impl fmt::Display for Capabilities {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut names: Vec<&str> = Capabilities::NAMES.iter().filter(|&&(flag, _)| { self.contains(flag) }).map(|&(_, name)| { name }).collect();
+		let known = Capabilities::NAMES.iter().fold(0, |known, &(flag, _)| { known | flag.0 });
+		if self.0 & !known != 0 {
+			names.push("unknown");
+		}
+
+		if names.is_empty() {
+			write!(f, "none")
+		}
+		else {
+			write!(f, "{}", names.join(", "))
+		}
+	}
+}
+
+/// What a program tells its peer about itself when the connection is established.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Handshake {
+	/// The version of the protocol the program speaks.
+	pub version: u16,
+	/// The oldest version the program can still talk to.
+	pub min_version: u16,
+	pub capabilities: Capabilities
+}
+
+/// The reasons two programs cannot talk to each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Incompatibility {
+	/// The peer is too old, it would have to speak at least the version provided.
+	PeerOutdated(u16),
+	/// This program is too old for the peer, it would have to speak at least the version provided.
+	Outdated(u16)
+}
+
+impl Handshake {
+	/// The handshake of this program.
+	pub fn new() -> Handshake {
+		Handshake {
+			version: PROTOCOL_VERSION,
+			min_version: MIN_PROTOCOL_VERSION,
+			capabilities: Capabilities::supported()
+		}
+	}
+
+	/// Check if this program can talk to the peer with the handshake provided. If so, the version
+	/// they will use is returned, which is the newer one both of them speak.
+	pub fn check(&self, peer: &Handshake) -> Result<u16, Incompatibility> {
+		if peer.version < self.min_version {
+			Err(Incompatibility::PeerOutdated(self.min_version))
+		}
+		else if self.version < peer.min_version {
+			Err(Incompatibility::Outdated(peer.min_version))
+		}
+		else {
+			Ok(self.version.min(peer.version))
+		}
+	}
+}
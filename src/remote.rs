@@ -15,7 +15,7 @@ pub enum DirSocket {
 /// The Remote however cannot be cloned, since that would undermine the
 /// safety we are trying to establish in the first place.
 pub struct Remote {
-	read: Mutex<TcpStream>,
+	read: Mutex<PacketReader<TcpStream>>,
 	write: Mutex<TcpStream>
 }
 
@@ -34,7 +34,7 @@ impl Remote {
 		// At this point, the assignment of the streams is completely arbitrary,
 		// since both streams could do the same work.
 		Ok(Remote {
-			read: Mutex::new(stream),
+			read: Mutex::new(PacketReader::new(stream)),
 			write: Mutex::new(stream_clone)
 		})
 	}
@@ -43,10 +43,10 @@ impl Remote {
 	/// If set to None, the part in question will block indefinately.
 	pub fn set_timeout(&self, timeout: Option<Duration>, dir: DirSocket) -> io::Result<()> {
 		match dir {
-			DirSocket::Read => self.read.lock().unwrap().set_read_timeout(timeout),
+			DirSocket::Read => self.read.lock().unwrap().get_ref().set_read_timeout(timeout),
 			DirSocket::Write => self.write.lock().unwrap().set_write_timeout(timeout),
 			DirSocket::Both => {
-				if let Err(err) = self.read.lock().unwrap().set_read_timeout(timeout) {
+				if let Err(err) = self.read.lock().unwrap().get_ref().set_read_timeout(timeout) {
 					return Err(err);
 				}
 
@@ -57,12 +57,12 @@ impl Remote {
 
 	/// Try to read the next incoming packet. This blocks until the stream is closed, the packet
 	/// has been read (With or without error) or the timeout is triggered.
-	/// Returns the packet if available and false, in case the stream has been
-	/// closed.
+	/// Returns the packet if available and Closed, in case the stream has been
+	/// closed. Parts of a packet that arrive before a timeout are kept for the next call.
 	pub fn read_packet(&self) -> Result<Packet, PacketReadError> {
 		let mut read_lock = self.read.lock().unwrap();
 
-		Packet::read_from_stream(&mut read_lock)
+		read_lock.read_packet()
 	}
 
 	/// Write the packet to the stream. Returns true if successful,
@@ -70,7 +70,13 @@ impl Remote {
 	pub fn write_packet(&self, p: &Packet) -> bool {
 		let mut write_lock = self.write.lock().unwrap();
 
-		p.write_to_stream(&mut write_lock)
+		match p.write_to_stream(&mut *write_lock) {
+			Ok(()) => true,
+			Err(err) => {
+				println!("Failed writing packet to stream: {:?}", err);
+				false
+			}
+		}
 	}
 
 	/// Shuts down the connection. After this it will be impossible to send
@@ -78,7 +84,42 @@ impl Remote {
 	pub fn shutdown(&self) {
 		// Reading and sending will be seperately shut down, as to not
 		// disturb any operation that might still be in the process.
-		self.read.lock().unwrap().shutdown(Shutdown::Read).expect("Error while shutting down read thread.");
+		self.read.lock().unwrap().get_ref().shutdown(Shutdown::Read).expect("Error while shutting down read thread.");
 		self.write.lock().unwrap().shutdown(Shutdown::Write).expect("Error while shutting down write thread.");
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+	use std::net::TcpListener;
+
+	#[test]
+	fn packet_survives_timeout() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let mut sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		let remote = Remote::new(listener.accept().unwrap().0).unwrap();
+		remote.set_timeout(Some(Duration::from_millis(50)), DirSocket::Read).unwrap();
+
+		let packet = Packet::Message(1, "Split in two".to_string());
+		let mut data = Vec::new();
+		packet.write_to_stream(&mut data).unwrap();
+
+		// Only half of the packet arrives before the timeout.
+		sender.write_all(&data[..data.len() / 2]).unwrap();
+		match remote.read_packet() {
+			Err(PacketReadError::IOError(_)) => {},
+			other => panic!("Expected a timeout, got {:?}", other)
+		}
+
+		sender.write_all(&data[data.len() / 2..]).unwrap();
+		assert_eq!(remote.read_packet().unwrap(), packet);
+
+		drop(sender);
+		match remote.read_packet() {
+			Err(PacketReadError::Closed) => {},
+			other => panic!("Expected the stream to be closed, got {:?}", other)
+		}
+	}
+}
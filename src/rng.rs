@@ -0,0 +1,41 @@
+//! A small and fast pseudo random number generator. It is good enough to make the computer players
+//! less predictable, but must never be used for anything that has to be secure.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Rng {
+	state: u64
+}
+
+impl Rng {
+	/// Create a generator that always produces the same numbers for the same seed.
+	pub fn new(seed: u64) -> Rng {
+		// The state may never be zero, otherwise only zeros would be generated.
+		Rng {
+			state: if seed == 0 { 0x9e3779b97f4a7c15 } else { seed }
+		}
+	}
+
+	/// Create a generator seeded with the current time.
+	pub fn from_time() -> Rng {
+		let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+			Ok(d) => d.as_secs().wrapping_mul(1000000000).wrapping_add(d.subsec_nanos() as u64),
+			Err(_) => 0
+		};
+
+		Rng::new(nanos)
+	}
+
+	/// The next random number (xorshift64*).
+	pub fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		self.state.wrapping_mul(0x2545f4914f6cdd1d)
+	}
+
+	/// A random number in the range from 0 up to, but not including, the bound.
+	pub fn below(&mut self, bound: u64) -> u64 {
+		assert!(bound > 0);
+		self.next_u64() % bound
+	}
+}
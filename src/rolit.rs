@@ -0,0 +1,190 @@
+//! Rolit, which is played by three or four players on an Othello board. Every player has their own
+//! colour and the turn goes around the table. A line of pieces of any other colours that is closed
+//! off by an own piece is flipped to the own colour. Whenever a player cannot flip anything, they
+//! may place their piece next to any piece on the board instead, so nobody ever has to pass and the
+//! game goes on until the board is full.
+use board::{Bitboard, DIRECTIONS, board_mask, bit_square, flipped, legal_moves, shift, square_bit, valid_size};
+use square::Square;
+
+/// The colour of a player, which is also their place in the turn order, starting at 0.
+pub type Colour = u8;
+
+/// The least and the most players a game can have.
+pub const MIN_PLAYERS: u8 = 3;
+pub const MAX_PLAYERS: u8 = 4;
+
+/// The names of the colours. The first two are the colours of Othello.
+pub const COLOUR_NAMES: [&'static str; MAX_PLAYERS as usize] = ["Black", "White", "Red", "Blue"];
+
+/// The reasons a piece may not be placed on a square.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RolitError {
+	/// It is the turn of another player.
+	WrongTurn,
+	/// The square is not on the board.
+	OffBoard,
+	/// There already is a piece on the square.
+	Occupied,
+	/// The square is not next to any piece on the board.
+	NotAdjacent,
+	/// The piece would not flip anything, although it could on another square.
+	MustFlip
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RolitBoard {
+	size: u8,
+	area: Bitboard,
+	/// The pieces of every colour, indexed by the colour.
+	pieces: Vec<Bitboard>,
+	turn: Colour,
+	/// The squares the pieces have been placed on, in order.
+	history: Vec<(Colour, (u8, u8))>
+}
+
+impl RolitBoard {
+	/// Create the start position for the number of players on a board with the side length
+	/// provided. The four squares in the middle get the colours in turn order, going clockwise, so
+	/// with three players one of them stays empty.
+	pub fn new(size: u8, players: u8) -> RolitBoard {
+		assert!(valid_size(size) && players >= MIN_PLAYERS && players <= MAX_PLAYERS);
+
+		let c = size / 2;
+		let centre = [(c - 1, c - 1), (c, c - 1), (c, c), (c - 1, c)];
+		let pieces = (0..players as usize).map(|colour| { square_bit(centre[colour]) }).collect();
+
+		RolitBoard {
+			size: size,
+			area: board_mask(size),
+			pieces: pieces,
+			turn: 0,
+			history: Vec::new()
+		}
+	}
+
+	pub fn size(&self) -> u8 {
+		self.size
+	}
+
+	/// The number of players, which is the number of colours on the board.
+	pub fn players(&self) -> u8 {
+		self.pieces.len() as u8
+	}
+
+	pub fn turn(&self) -> Colour {
+		self.turn
+	}
+
+	pub fn on_board(&self, (x, y): (u8, u8)) -> bool {
+		x < self.size && y < self.size
+	}
+
+	pub fn pieces(&self, colour: Colour) -> Bitboard {
+		self.pieces[colour as usize]
+	}
+
+	pub fn count(&self, colour: Colour) -> u8 {
+		self.pieces(colour).count_ones() as u8
+	}
+
+	/// All squares that have a piece of any colour on them.
+	pub fn occupied(&self) -> Bitboard {
+		self.pieces.iter().fold(0, |occupied, &pieces| { occupied | pieces })
+	}
+
+	/// The pieces of all colours but the one provided. They can all be flipped by it.
+	fn others(&self, colour: Colour) -> Bitboard {
+		self.occupied() & !self.pieces(colour)
+	}
+
+	/// All squares the colour could place a piece on. These are the ones flipping something, or
+	/// if there are none, all empty squares next to a piece.
+	pub fn legal_moves(&self, colour: Colour) -> Bitboard {
+		let flipping = legal_moves(self.pieces(colour), self.others(colour), self.area);
+		if flipping != 0 {
+			return flipping;
+		}
+
+		let occupied = self.occupied();
+		let adjacent = DIRECTIONS.iter().fold(0, |adjacent, &dir| { adjacent | shift(occupied, dir) });
+		adjacent & self.area & !occupied
+	}
+
+	/// The squares of all legal moves of the colour.
+	pub fn opportunities(&self, colour: Colour) -> Vec<(u8, u8)> {
+		let mut moves = self.legal_moves(colour);
+		let mut squares = Vec::with_capacity(moves.count_ones() as usize);
+		while moves != 0 {
+			squares.push(bit_square(moves.trailing_zeros()));
+			moves &= moves - 1;
+		}
+
+		squares
+	}
+
+	/// Place a piece of the colour whose turn it is on the square and flip all lines it closes off.
+	/// Returns the pieces that have been flipped.
+	pub fn place(&mut self, (x, y): (u8, u8), colour: Colour) -> Result<Bitboard, RolitError> {
+		if colour != self.turn {
+			return Err(RolitError::WrongTurn);
+		}
+		if !self.on_board((x, y)) {
+			return Err(RolitError::OffBoard);
+		}
+
+		let square = square_bit((x, y));
+		if self.occupied() & square != 0 {
+			return Err(RolitError::Occupied);
+		}
+
+		if self.legal_moves(colour) & square == 0 {
+			// Either the piece would not touch any other, or another square would flip something.
+			let own = self.pieces(colour);
+			return Err(if legal_moves(own, self.others(colour), self.area) != 0 { RolitError::MustFlip } else { RolitError::NotAdjacent });
+		}
+
+		let flips = flipped(square, self.pieces(colour), self.others(colour));
+		for pieces in &mut self.pieces {
+			*pieces &= !flips;
+		}
+		self.pieces[colour as usize] |= square | flips;
+
+		self.history.push((colour, (x, y)));
+		self.turn = (self.turn + 1) % self.players();
+
+		Ok(flips)
+	}
+
+	/// The game is over once the player to move cannot place a piece anywhere, which only happens
+	/// when the board is full.
+	pub fn finished(&self) -> bool {
+		self.legal_moves(self.turn) == 0
+	}
+
+	/// The colours with the most pieces. There is more than one in case of a tie.
+	pub fn leaders(&self) -> Vec<Colour> {
+		let most = (0..self.players()).map(|colour| { self.count(colour) }).max().unwrap_or(0);
+		(0..self.players()).filter(|&colour| { self.count(colour) == most }).collect()
+	}
+
+	/// The square of the last piece that has been placed on the board.
+	pub fn last_placed(&self) -> Option<(u8, u8)> {
+		self.history.last().map(|&(_, square)| { square })
+	}
+
+	/// The colour of the piece on the square, if there is one.
+	pub fn square(&self, (x, y): (u8, u8)) -> Option<Colour> {
+		if !self.on_board((x, y)) {
+			return None;
+		}
+
+		let square = square_bit((x, y));
+		self.pieces.iter().position(|&pieces| { pieces & square != 0 }).map(|colour| { colour as Colour })
+	}
+
+	/// The squares of all moves that have been made, in the same notation as Othello transcripts.
+	/// Since nobody passes, the colour of every move follows from its place in the list.
+	pub fn transcript(&self) -> String {
+		self.history.iter().map(|&(_, square)| { Square::from(square).to_string() }).collect()
+	}
+}
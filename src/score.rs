@@ -1,32 +1,172 @@
-use board::{Board, Piece};
+use std::str::FromStr;
+use board::{Board, Piece, Status};
+
+/// Decides which player wins once the game is over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Objective {
+	/// The player with the most pieces wins, as in normal Othello.
+	MostPieces,
+	/// The player with the fewest pieces wins. This is the misère variant, also called
+	/// anti-Othello.
+	FewestPieces
+}
+
+/// The result of a game that has ended, including the final number of pieces of both players.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+	BlackWins { black: u8, white: u8 },
+	WhiteWins { black: u8, white: u8 },
+	Draw { black: u8, white: u8 }
+}
+
+impl GameResult {
+	/// Decide the result of the game by the final number of pieces, the player with the most
+	/// pieces winning.
+	pub fn from_counts(black: u8, white: u8) -> GameResult {
+		if black > white {
+			GameResult::BlackWins { black: black, white: white }
+		}
+		else if white > black {
+			GameResult::WhiteWins { black: black, white: white }
+		}
+		else {
+			GameResult::Draw { black: black, white: white }
+		}
+	}
+
+	/// Decide the result of the game by the final number of pieces and the objective.
+	pub fn decide(black: u8, white: u8, objective: Objective) -> GameResult {
+		match objective {
+			Objective::MostPieces => GameResult::from_counts(black, white),
+			Objective::FewestPieces => match GameResult::from_counts(black, white) {
+				GameResult::BlackWins { .. } => GameResult::WhiteWins { black: black, white: white },
+				GameResult::WhiteWins { .. } => GameResult::BlackWins { black: black, white: white },
+				draw => draw
+			}
+		}
+	}
+
+	/// The colour of the winner, or None in case of a draw.
+	pub fn winner(&self) -> Option<Piece> {
+		match self {
+			&GameResult::BlackWins { .. } => Some(Piece::Black),
+			&GameResult::WhiteWins { .. } => Some(Piece::White),
+			&GameResult::Draw { .. } => None
+		}
+	}
+
+	pub fn black(&self) -> u8 {
+		match self {
+			&GameResult::BlackWins { black, .. } => black,
+			&GameResult::WhiteWins { black, .. } => black,
+			&GameResult::Draw { black, .. } => black
+		}
+	}
+
+	pub fn white(&self) -> u8 {
+		match self {
+			&GameResult::BlackWins { white, .. } => white,
+			&GameResult::WhiteWins { white, .. } => white,
+			&GameResult::Draw { white, .. } => white
+		}
+	}
+}
+
+/// The way the pieces are counted once the game has ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scoring {
+	/// Only the pieces on the board are counted.
+	Pieces,
+	/// The empty squares are counted for the winner, as it is done in tournaments. In case of a
+	/// draw, they are split evenly, with an odd one going to white, who has moved second. When
+	/// the fewest pieces win, they are counted for the loser instead, so the winner keeps having
+	/// fewer.
+	EmptiesToWinner
+}
+
+impl Scoring {
+	/// Calculate the result of a finished game with the number of pieces and empty squares on the
+	/// board, deciding the winner by the objective.
+	pub fn result(&self, black: u8, white: u8, empty: u8, objective: Objective) -> GameResult {
+		let result = GameResult::decide(black, white, objective);
+		match self {
+			&Scoring::Pieces => result,
+			&Scoring::EmptiesToWinner => {
+				// The empty squares go to the player with more pieces, who is the loser if the
+				// fewest pieces win.
+				match (result, objective) {
+					(GameResult::BlackWins { .. }, Objective::MostPieces) => GameResult::BlackWins { black: black + empty, white: white },
+					(GameResult::BlackWins { .. }, Objective::FewestPieces) => GameResult::BlackWins { black: black, white: white + empty },
+					(GameResult::WhiteWins { .. }, Objective::MostPieces) => GameResult::WhiteWins { black: black, white: white + empty },
+					(GameResult::WhiteWins { .. }, Objective::FewestPieces) => GameResult::WhiteWins { black: black + empty, white: white },
+					// Blocked squares can leave an odd number of empty squares even in a draw.
+					(GameResult::Draw { .. }, _) => GameResult::Draw { black: black + empty / 2, white: white + empty - empty / 2 }
+				}
+			}
+		}
+	}
+
+	/// Read the scoring from an optional configuration entry. Falls back to counting the pieces,
+	/// in case the entry is missing or invalid.
+	pub fn from_config(entry: &Option<String>) -> Scoring {
+		match entry {
+			&Some(ref s) => match s.parse() {
+				Ok(scoring) => scoring,
+				Err(err) => {
+					println!("[WARNING] {} Falling back to counting pieces.", err);
+					Scoring::Pieces
+				}
+			},
+			&None => Scoring::Pieces
+		}
+	}
+}
+
+impl FromStr for Scoring {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Scoring, String> {
+		match s {
+			"pieces" => Ok(Scoring::Pieces),
+			"tournament" => Ok(Scoring::EmptiesToWinner),
+			s => Err(format!("Unknown scoring '{}'. Expected 'pieces' or 'tournament'.", s))
+		}
+	}
+}
 
 /// Bar to keep track of the current score between the two teams.
 pub struct Score<'a> {
 	board: &'a Board,
+	scoring: Scoring,
+	objective: Objective,
 	white: u8,
 	black: u8
 }
 
 impl<'a> Score<'a> {
+	/// Score the board, counting only the pieces on the board.
 	pub fn score(board: &'a Board) -> Score<'a> {
-		let mut white = 0;
-		let mut black = 0;
-
-		for square in board.squares().iter().flat_map(|v| {v.iter()}) {
-			match square {
-				&Some(Piece::Black) => black += 1,
-				&Some(Piece::White) => white += 1,
-				_ => {}
-			}
-		}
+		Score::with_scoring(board, Scoring::Pieces)
+	}
 
+	/// Score the board. The scoring is used to calculate the result once the game has ended, in
+	/// which the player with the most pieces wins.
+	pub fn with_scoring(board: &'a Board, scoring: Scoring) -> Score<'a> {
 		Score {
 			board: board,
-			white: white,
-			black: black
+			scoring: scoring,
+			objective: Objective::MostPieces,
+			white: board.count(Piece::White),
+			black: board.count(Piece::Black)
 		}
 	}
 
+	/// Decide the winner by the objective, for instance to play misère.
+	pub fn with_objective(mut self, objective: Objective) -> Score<'a> {
+		self.objective = objective;
+		self
+	}
+
 	pub fn white(&self) -> u8 {
 		self.white
 	}
@@ -35,27 +175,15 @@ impl<'a> Score<'a> {
 		self.black
 	}
 
-	/// Determine the winner of the game. Returns None in case the winner is unclear
-	/// as of yet, otherwise the colour of the winner. This is strictly by stones,
-	/// however. The ultimate winner may be decided somewhere else, for instance when
-	/// one player forfeits the game.
-	pub fn winner(&self) -> Option<Piece> {
-		// The winner will be determined when both players are out of options, i.e. the board
+	/// Determine the result of the game. Returns None in case the game is still running. This is
+	/// strictly by stones, however. The ultimate winner may be decided somewhere else, for
+	/// instance when one player forfeits the game.
+	pub fn result(&self) -> Option<GameResult> {
+		// The result will be determined when both players are out of options, i.e. the board
 		// cannot change any more.
-		if !self.board.opportunities(Piece::Black).is_empty() {
-			return None;
-		}
-		if !self.board.opportunities(Piece::White).is_empty() {
-			return None;
-		}
-
-		// White has one when both parties have the same amount of stones, because black always has
-		// the first move.
-		if self.white >= self.black {
-			Some(Piece::White)
-		}
-		else {
-			Some(Piece::Black)
+		match self.board.status() {
+			Status::Finished { .. } => Some(self.scoring.result(self.black, self.white, self.board.empty_count(), self.objective)),
+			_ => None
 		}
 	}
 
@@ -63,3 +191,18 @@ impl<'a> Score<'a> {
 		(self.white, self.black)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn drawn_empties_add_up() {
+		// 63 squares are left after blocking one, 30 pieces each and three empty squares.
+		for &objective in &[Objective::MostPieces, Objective::FewestPieces] {
+			let result = Scoring::EmptiesToWinner.result(30, 30, 3, objective);
+			assert_eq!(result, GameResult::Draw { black: 31, white: 32 });
+			assert_eq!(result.black() + result.white(), 63);
+		}
+	}
+}
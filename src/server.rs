@@ -6,10 +6,23 @@ extern crate toml;
 #[macro_use]
 extern crate lazy_static;
 
+pub mod ai;
+pub mod analysis;
 pub mod board;
+pub mod events;
+pub mod handicap;
+pub mod layout;
 pub mod packets;
+pub mod protocol;
 pub mod remote;
+pub mod rng;
+pub mod rolit;
+pub mod score;
+pub mod square;
+pub mod start;
 pub mod srv;
+pub mod symmetry;
+pub mod transcript;
 
 use std::thread;
 use std::time::Duration;
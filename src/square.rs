@@ -0,0 +1,69 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A square on the board, which can be written in algebraic notation. The columns are the letters
+/// starting with 'a' on the left side, the rows are numbered from the top, starting with 1. The
+/// upper left corner is therefore a1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Square {
+	pub x: u8,
+	pub y: u8
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+	/// There was no square to parse at all.
+	Empty,
+	/// The column has to be a letter between a and z.
+	InvalidColumn(char),
+	/// The row has to be a number starting with 1.
+	InvalidRow(String)
+}
+
+impl Square {
+	pub fn new(x: u8, y: u8) -> Square {
+		Square {
+			x: x,
+			y: y
+		}
+	}
+
+	/// The position of the square as it is used by the board.
+	pub fn pos(&self) -> (u8, u8) {
+		(self.x, self.y)
+	}
+}
+
+impl From<(u8, u8)> for Square {
+	fn from((x, y): (u8, u8)) -> Square {
+		Square::new(x, y)
+	}
+}
+
+impl fmt::Display for Square {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", (b'a' + self.x) as char, self.y as u16 + 1)
+	}
+}
+
+impl FromStr for Square {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Square, ParseError> {
+		let mut chars = s.chars();
+		let column = match chars.next() {
+			Some(c) => c.to_ascii_lowercase(),
+			None => return Err(ParseError::Empty)
+		};
+
+		if column < 'a' || column > 'z' {
+			return Err(ParseError::InvalidColumn(column));
+		}
+
+		let row = chars.as_str();
+		match row.parse::<u8>() {
+			Ok(r) if r >= 1 => Ok(Square::new(column as u8 - b'a', r - 1)),
+			_ => Err(ParseError::InvalidRow(row.to_string()))
+		}
+	}
+}
@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io;
 use toml;
+use score::Scoring;
 
 lazy_static! {
 	pub static ref CONFIG: Config = {
@@ -22,10 +23,16 @@ pub enum ReadError {
 #[derive(Deserialize)]
 pub struct Config {
 	pub port: u16,
-	pub max_clients: usize
+	pub max_clients: usize,
+	pub scoring: Option<String>
 }
 
 impl Config {
+	/// The way the pieces are counted when reporting the result of a game.
+	pub fn scoring(&self) -> Scoring {
+		Scoring::from_config(&self.scoring)
+	}
+
 	// Load the configuration from the toml file dedicated to the server.
 	pub fn load() -> Result<Config, ReadError> {
 		let mut file = match File::open("server.toml") {
@@ -1,15 +1,46 @@
 use std::sync::{Arc, Weak, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use board::*;
+use events::{BoardEvent, Observer, ObservedBoard};
 use std::thread;
 use std::time::Duration;
 use std::collections::VecDeque;
 use packets::*;
+use score::*;
+use square::Square;
 
-use super::NetClient;
+use super::{CONFIG, NetClient};
+
+/// Logs the moves of a game, naming the clients that have made them.
+struct GameLog {
+	black: ClientId,
+	white: ClientId
+}
+
+impl GameLog {
+	fn client(&self, piece: Piece) -> ClientId {
+		match piece {
+			Piece::Black => self.black,
+			Piece::White => self.white
+		}
+	}
+}
+
+impl Observer for GameLog {
+	fn notify(&mut self, event: &BoardEvent) -> bool {
+		match event {
+			&BoardEvent::Placed { piece, square } => println!("Client [{}] places at {}.", self.client(piece), Square::from(square)),
+			&BoardEvent::Passed(piece) => println!("Client [{}] has to pass.", self.client(piece)),
+			_ => {}
+		}
+
+		true
+	}
+}
 
 pub struct Game {
-    board: Mutex<Board>,
+    board: Mutex<ObservedBoard>,
+    options: GameOptions,
     black: Weak<NetClient>,
     black_packets: Arc<Mutex<VecDeque<Packet>>>,
     white: Weak<NetClient>,
@@ -20,8 +51,8 @@ pub struct Game {
 impl Game {
     /// Create (and start) a new game between the two clients provided. This will spawn a new
     /// thread and handle the entire game-flow. The Weak-pointer to the game will expire when
-    /// the game has ended.
-    pub fn new(black: Weak<NetClient>, white: Weak<NetClient>) -> Option<Weak<Game>> {
+    /// the game has ended. The options must be valid and resolved.
+    pub fn new(black: Weak<NetClient>, white: Weak<NetClient>, options: GameOptions) -> Option<Weak<Game>> {
         let black_arc = match black.upgrade() {
             Some(arc) => arc,
             None => return None
@@ -33,8 +64,8 @@ impl Game {
         };
 
         // The game can be started. Send the information to both the clients.
-        black_arc.send(&Packet::StartGame(white_arc.id(), Piece::Black));
-        white_arc.send(&Packet::StartGame(black_arc.id(), Piece::White));
+        black_arc.send(&Packet::StartGame(white_arc.id(), Piece::Black, options.clone()));
+        white_arc.send(&Packet::StartGame(black_arc.id(), Piece::White, options.clone()));
 
         // Subscribe to both clients.
         let black_packets = Arc::new(Mutex::new(VecDeque::new()));
@@ -42,8 +73,20 @@ impl Game {
         black_arc.subscribe(Arc::downgrade(&black_packets));
         white_arc.subscribe(Arc::downgrade(&white_packets));
 
+        // The board is created with the pieces of the handicap and the start position on it,
+        // exactly like the clients create theirs.
+        if let Some(handicap) = options.handicap {
+            println!("Client [{}] plays black and [{}] white with a handicap of {}.", black_arc.id(), white_arc.id(), handicap);
+        }
+        let mut board = ObservedBoard::new(options.board()).with_objective(options.objective).with_scoring(CONFIG.scoring());
+        board.subscribe(Box::new(GameLog {
+            black: black_arc.id(),
+            white: white_arc.id()
+        }));
+
         let game = Arc::new(Game {
-            board: Mutex::new(Board::new()),
+            board: Mutex::new(board),
+            options: options,
             black: black,
             black_packets: black_packets,
             white: white,
@@ -103,11 +146,35 @@ impl Game {
 				}
 
 				// The stone can now be tried to set on the board, to check if it is a valid move.
-				if self.board.lock().unwrap().place((x, y), piece) {
-					// Inform the opponent of the move.
-					if !opponent.send(&Packet::PlacePiece(player.id(), x, y)) {
-						panic!("Could not send packet to opponent, leaving board in illegal state.");
-					}
+				let mut board_lock = self.board.lock().unwrap();
+				match board_lock.place((x, y), piece) {
+					Ok(_) => {
+						// Inform the opponent of the move. The clients pass automatically as well,
+						// so a pass that follows does not have to be sent.
+						if !opponent.send(&Packet::PlacePiece(player.id(), x, y)) {
+							panic!("Could not send packet to opponent, leaving board in illegal state.");
+						}
+
+						if let Some(result) = Score::with_scoring(&board_lock, CONFIG.scoring()).with_objective(self.options.objective).result() {
+							let (black, white) = match piece {
+								Piece::Black => (player.id(), opponent.id()),
+								Piece::White => (opponent.id(), player.id())
+							};
+							match result {
+								GameResult::BlackWins { black: b, white: w } => println!("Client [{}] has won against [{}] with {}:{}.", black, white, b, w),
+								GameResult::WhiteWins { black: b, white: w } => println!("Client [{}] has won against [{}] with {}:{}.", white, black, w, b),
+								GameResult::Draw { black: b, white: w } => println!("The game between [{}] and [{}] has ended in a draw with {}:{}.", black, white, b, w)
+							}
+
+							// A win with the help of a handicap means less than one without.
+							if let Some(handicap) = self.options.handicap {
+								println!("The game was played with a handicap of {}.", handicap);
+							}
+
+							println!("Transcript: {}", board_lock.transcript());
+						}
+					},
+					Err(err) => println!("[WARNING] Refused move of client [{}] at ({}, {}): {:?}", player.id(), x, y, err)
 				}
 			},
 			Packet::Pass(opponent_id) => {
@@ -120,14 +187,16 @@ impl Game {
 					return;
 				}
 
-				// Check that the player who tried to pass is the one whos turn it is.
+				// Check that the player who tried to pass is the one whos turn it is and that they
+				// really cannot place a piece anywhere.
 				let mut board_lock = self.board.lock().unwrap();
-				if board_lock.turn() == piece {
-					// Pass for the client who sent the packet and let the other client know that
-					// their opponent has passed.
-					board_lock.pass();
+				if board_lock.turn() == piece && board_lock.pass() {
+					// Let the other client know that their opponent has passed.
 					opponent.send(&Packet::Pass(player.id()));
 				}
+				else {
+					println!("[WARNING] Refused pass of client [{}], since it is not allowed to pass.", player.id());
+				}
 			},
 			Packet::AbandonGame(opponent_id) => {
 				// Note that whos turn it is doesn't matter here, so we just reference by blacks
@@ -178,6 +247,11 @@ impl Game {
 			return false;
 		}
 
+		// The game is over as soon as neither player can place a piece any more.
+		if let Status::Finished { .. } = self.board.lock().unwrap().status() {
+			return false;
+		}
+
 		// Check that both players are still connected.
 		self.white.upgrade().is_some() && self.black.upgrade().is_some()
     }
@@ -1,14 +1,28 @@
 use std::sync::{Arc, Weak, Mutex};
-use super::{Game, NetHandler};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use super::{Game, NetHandler, RolitGame};
 use packets::*;
-use std::collections::{HashSet, VecDeque};
+use protocol::Capabilities;
+use rng::Rng;
+use rolit::{MIN_PLAYERS, MAX_PLAYERS};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub struct GameHandler {
     nethandler: Arc<NetHandler>,
     games: Vec<Weak<Game>>,
+    rolit_games: Vec<Weak<RolitGame>>,
     /// All pending requests the first id is the requester, the second the requestee who has not
-    /// yet answered.
-    pending: HashSet<(ClientId, ClientId)>,
+    /// yet answered, with the options the requester would like to play with.
+    pending: HashMap<(ClientId, ClientId), GameOptions>,
+    /// All games of Rolit that have not yet been accepted by every player, with the players who
+    /// already have.
+    pending_rolit: HashMap<Vec<ClientId>, HashSet<ClientId>>,
+    /// Chooses the blocked squares of games with a random layout.
+    rng: Rng,
+    /// The games that have been started by the threads resolving their options.
+    started: Receiver<Weak<Game>>,
+    start_sender: Sender<Weak<Game>>,
     packets: Arc<Mutex<VecDeque<(ClientId, Packet)>>>
 }
 
@@ -18,11 +32,17 @@ impl GameHandler {
         // naturally nothing has been requested yet.
         let packets = Arc::new(Mutex::new(VecDeque::new()));
         nethandler.subscribe(Arc::downgrade(&packets));
+        let (start_sender, started) = mpsc::channel();
 
         GameHandler {
             nethandler: nethandler,
             games: Vec::new(),
-            pending: HashSet::new(),
+            rolit_games: Vec::new(),
+            pending: HashMap::new(),
+            pending_rolit: HashMap::new(),
+            rng: Rng::from_time(),
+            started: started,
+            start_sender: start_sender,
             packets: packets
         }
     }
@@ -36,53 +56,82 @@ impl GameHandler {
 
             match packet {
                 Packet::Disconnect => self.handle_disconnect(client),
-                Packet::RequestGame(to) => self.handle_game_request(client, to),
+                Packet::RequestGame(to, options) => self.handle_game_request(client, to, options),
                 Packet::DenyGame(to) => self.handle_deny_game(client, to),
+                Packet::RequestRolit(players) => self.handle_rolit_request(client, players),
                 _ => {}
             }
         }
 
+		self.games.extend(self.started.try_iter());
+
 		// Check for games that are no longer running, to prevent memory leakage in form of the
 		// Games-Vector just groing with long dead games.
 		self.games.retain(|ref game| { game.upgrade().is_some() });
+		self.rolit_games.retain(|ref game| { game.upgrade().is_some() });
     }
 
     fn handle_disconnect(&mut self, client: ClientId) {
         // All game requests to the client will be denied.
-        for &(from, to) in &self.pending {
+        for &(from, to) in self.pending.keys() {
             if to == client {
                 self.nethandler.send(from, &Packet::DenyGame(to));
             }
         }
 
         // Remove all game requests the client was involved in.
-        self.pending.retain(|&(ref from, ref to)| { *from != client && *to != client });
+        self.pending.retain(|&(ref from, ref to), _| { *from != client && *to != client });
+
+        // Games of Rolit cannot be played without the client either.
+        self.cancel_rolit(client, |players| { players.contains(&client) });
     }
 
-    fn handle_game_request(&mut self, from: ClientId, to: ClientId) {
-        // In case the request has already been made, it can be ignored.
-        if self.pending.contains(&(from, to)) {
-            println!("Duplicate game request from [{}] to [{}] was ignored. Still awaiting answer.", from, to);
+    fn handle_game_request(&mut self, from: ClientId, to: ClientId, options: GameOptions) {
+        if !options.valid() {
+            println!("[WARNING] Blocked game request from [{}] to [{}] with invalid options {:?}.", from, to, options);
             return;
         }
 
-        if self.pending.contains(&(to, from)) {
-            self.pending.remove(&(to, from));
+        // Clients that only know the standard game would not understand the request.
+        if options != GameOptions::new() && !self.nethandler.supports(to, Capabilities::VARIANTS) {
+            println!("Denied game request from [{}] to [{}], since [{}] does not support other options than the standard ones.", from, to, to);
+            self.nethandler.send(from, &Packet::DenyGame(to));
+            return;
+        }
 
-            println!("Starting game between [{}] and [{}]", from, to);
+        // In case the request has already been made, it can be ignored.
+        if self.pending.get(&(from, to)) == Some(&options) {
+            println!("Duplicate game request from [{}] to [{}] was ignored. Still awaiting answer.", from, to);
+            return;
+        }
 
-            // There has been no explicit response, but since both have requested a game from the
-            // other client, we can assume that the game can be started.
-            self.start_game(from, to);
+        match self.pending.remove(&(to, from)) {
+            Some(requested) if requested == options => {
+                println!("Starting game between [{}] and [{}] on a {}", from, to, options);
+
+                // There has been no explicit response, but since both have requested a game from
+                // the other client with the same options, we can assume that the game can be
+                // started.
+                self.start_game(from, to, options);
+                return;
+            },
+            // The request has been answered with different options, which replace the old ones.
+            Some(_) => println!("Client [{}] has answered the game request of [{}] with different options.", from, to),
+            None => {}
         }
 
-        self.pending.insert((from, to));
-        println!("Added game request from [{}] to [{}]", from, to);
-        self.nethandler.send(to, &Packet::RequestGame(from));
+        println!("Added game request from [{}] to [{}] on a {}", from, to, options);
+        self.nethandler.send(to, &Packet::RequestGame(from, options.clone()));
+        self.pending.insert((from, to), options);
     }
 
     fn handle_deny_game(&mut self, from: ClientId, to: ClientId) {
-		if !self.pending.remove(&(to, from)) {
+		// The client might be declining an invitation to Rolit instead.
+		if self.cancel_rolit(from, |players| { players[0] == to && players.contains(&from) }) {
+			return;
+		}
+
+		if self.pending.remove(&(to, from)).is_none() {
 			// There was no request, so there is nothing to deny.
 			println!("[WARNING] Blocked DenyGame packet, since there has never been a request.");
 			return;
@@ -92,7 +141,75 @@ impl GameHandler {
 		self.nethandler.send(to, &Packet::DenyGame(from));
     }
 
-    fn start_game(&mut self, client1: ClientId, client2: ClientId) {
+    fn handle_rolit_request(&mut self, from: ClientId, players: Vec<ClientId>) {
+        // Everyone must be on the list exactly once, including the one sending it.
+        let unique: HashSet<ClientId> = players.iter().cloned().collect();
+        if players.len() < MIN_PLAYERS as usize || players.len() > MAX_PLAYERS as usize || unique.len() != players.len() || !unique.contains(&from) {
+            println!("[WARNING] Blocked Rolit request from [{}] with invalid players {:?}.", from, players);
+            return;
+        }
+
+        // Everyone has to be able to play Rolit, otherwise the invitation is declined for them.
+        if let Some(&player) = players.iter().find(|&&player| { !self.nethandler.supports(player, Capabilities::ROLIT) }) {
+            println!("Denied Rolit request from [{}], since [{}] does not support Rolit.", from, player);
+            self.nethandler.send(from, &Packet::DenyGame(player));
+            return;
+        }
+
+        let accepted = {
+            let accepted = self.pending_rolit.entry(players.clone()).or_insert(HashSet::new());
+            if !accepted.insert(from) {
+                println!("Duplicate Rolit request from [{}] was ignored. Still awaiting answers.", from);
+                return;
+            }
+            accepted.len()
+        };
+
+        // The first one to ask invites everyone else.
+        if accepted == 1 {
+            println!("Client [{}] has invited {:?} to a game of Rolit.", from, players);
+            for &player in players.iter().filter(|&&player| { player != from }) {
+                self.nethandler.send(player, &Packet::RequestRolit(players.clone()));
+            }
+        }
+
+        if accepted == players.len() {
+            println!("Starting a game of Rolit between {:?}", players);
+            self.pending_rolit.remove(&players);
+            self.start_rolit(players);
+        }
+    }
+
+    /// Remove the pending games of Rolit the filter applies to and tell everyone who has already
+    /// accepted them that the client has declined. Returns if there have been any.
+    fn cancel_rolit<F: Fn(&Vec<ClientId>) -> bool>(&mut self, client: ClientId, filter: F) -> bool {
+        let cancelled: Vec<Vec<ClientId>> = self.pending_rolit.keys().filter(|players| { filter(players) }).cloned().collect();
+        for players in &cancelled {
+            for &player in &self.pending_rolit.remove(players).unwrap() {
+                if player != client {
+                    self.nethandler.send(player, &Packet::DenyGame(client));
+                }
+            }
+        }
+
+        !cancelled.is_empty()
+    }
+
+    fn start_rolit(&mut self, players: Vec<ClientId>) {
+        let mut clients = Vec::with_capacity(players.len());
+        for player in players {
+            match self.nethandler.get_client(player) {
+                Some(c) => clients.push(c),
+                None => return
+            }
+        }
+
+        if let Some(game) = RolitGame::new(clients) {
+            self.rolit_games.push(game);
+        }
+    }
+
+    fn start_game(&mut self, client1: ClientId, client2: ClientId, options: GameOptions) {
         let client1 = match self.nethandler.get_client(client1) {
             Some(c) => c,
             None => return
@@ -103,12 +220,17 @@ impl GameHandler {
             None => return
         };
 
-        let game = match Game::new(client1, client2) {
-            Some(g) => g,
-            None => return
-        };
-
-        // The game has been started successfully. Add it to the games of this GameHandler.
-        self.games.push(game);
+        // Both players have to get the same blocked squares and start position, so they are
+        // chosen once here. Finding a balanced random start takes up to a few hundred searches of
+        // the computer player, so it is done on its own thread to keep the other clients from
+        // waiting. The game is added to the games of this GameHandler once it has been started.
+        let mut rng = Rng::new(self.rng.next_u64());
+        let started = self.start_sender.clone();
+        thread::spawn(move || {
+            let options = options.resolved(&mut rng);
+            if let Some(game) = Game::new(client1, client2, options) {
+                let _ = started.send(game);
+            }
+        });
     }
 }
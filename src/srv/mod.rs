@@ -15,3 +15,6 @@ pub use self::netclient::*;
 
 pub mod nethandler;
 pub use self::nethandler::*;
+
+pub mod rolit_game;
+pub use self::rolit_game::*;
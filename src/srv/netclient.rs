@@ -1,6 +1,7 @@
 use std::sync::{Arc, Weak, Mutex, RwLock};
 use remote::Remote;
 use packets::*;
+use protocol::Capabilities;
 use std::thread::{self, JoinHandle};
 use super::nethandler::*;
 use std::collections::VecDeque;
@@ -10,6 +11,7 @@ pub struct NetClient {
     id: ClientId,
     remote: Arc<Remote>,
 	packets: ArcRw<Vec<Weak<Mutex<VecDeque<Packet>>>>>, // Receiver of all the packets of this client.
+    capabilities: Capabilities, // What the client has announced in its handshake.
     pt_handle: Option<JoinHandle<()>> // JoinHandle of the packet thread.
 }
 
@@ -18,7 +20,8 @@ impl NetClient {
     /// id is the clients id on the NetHandler.
     /// Remote is the socket the client will receive packets from and send packets to.
     /// global_bus is the bus, where all packets of all clients will be sent to.
-    pub (super) fn start(nethandler: Arc<NetHandler>, id: ClientId, remote: Remote) -> NetClient {
+    /// capabilities are the ones the client has announced in its handshake.
+    pub (super) fn start(nethandler: Arc<NetHandler>, id: ClientId, remote: Remote, capabilities: Capabilities) -> NetClient {
         let remote = Arc::new(remote);
 		let packets = Arc::new(RwLock::new(Vec::new()));
 
@@ -73,6 +76,7 @@ impl NetClient {
             id: id,
             remote: remote,
 			packets: packets,
+            capabilities: capabilities,
             pt_handle: Some(pt_handle)
         }
     }
@@ -121,4 +125,9 @@ impl NetClient {
     pub fn id(&self) -> ClientId {
         self.id
     }
+
+    /// The optional features the client supports.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
 }
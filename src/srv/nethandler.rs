@@ -1,15 +1,20 @@
 use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
 use packets::*;
-use remote::Remote;
+use protocol::*;
+use remote::{DirSocket, Remote};
 use super::netclient::NetClient;
 use std::thread;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener};
 use std::collections::{HashMap, VecDeque};
 use std::io::Error as IOError;
+use std::time::Duration;
 
 pub type ArcRw<T> = Arc<RwLock<T>>;
 pub const SERVER_ID: ClientId = 0;
 
+/// How long a client has to send its Hello after connecting, before it is dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub enum Error {
 	AlreadyListening,
@@ -37,11 +42,29 @@ fn search_free_id<T>(map: &HashMap<ClientId, T>, start: ClientId) -> Option<Clie
 	None
 }
 
+/// Wait for the Hello of a freshly connected client and check if the server can talk to it.
+/// Returns the handshake of the client, or the reason it has to be rejected.
+fn receive_hello(remote: &Remote) -> Result<Handshake, String> {
+	let hello = match remote.read_packet() {
+		Ok(Packet::Hello(hello)) => hello,
+		Ok(p) => return Err(format!("Expected a Hello packet, but received {:?}.", p)),
+		Err(err) => return Err(format!("No Hello packet could be read: {:?}", err))
+	};
+
+	match Handshake::new().check(&hello) {
+		Ok(_) => Ok(hello),
+		Err(Incompatibility::PeerOutdated(needed)) => Err(format!("The client speaks protocol version {}, but the server needs at least version {}. Please update the client.", hello.version, needed)),
+		Err(Incompatibility::Outdated(needed)) => Err(format!("The server speaks protocol version {}, but the client needs at least version {}.", PROTOCOL_VERSION, needed))
+	}
+}
+
 /// Listens for clients and accepts them. The packets can then be queried by another thread.
 /// The NetHandler is designed to be cloned and shared between any number of threads.
 pub struct NetHandler {
     clients: RwLock<HashMap<ClientId, Arc<NetClient>>>,
-	packets: RwLock<Vec<Weak<Mutex<VecDeque<(ClientId, Packet)>>>>>
+	packets: RwLock<Vec<Weak<Mutex<VecDeque<(ClientId, Packet)>>>>>,
+	// The id that has been given to a client last. New ids are searched for after it.
+	last_id: Mutex<ClientId>
 }
 
 impl NetHandler {
@@ -54,15 +77,20 @@ impl NetHandler {
 				Err(err) => return Err(Error::SockErr(err))
 		};
 
+		Ok(NetHandler::listen(listener))
+    }
+
+    /// Start accepting clients on a listener that is already bound. Like start_listen, this
+    /// starts another thread.
+    pub fn listen(listener: TcpListener) -> Arc<NetHandler> {
 		let nethandler = Arc::new(NetHandler {
 			clients: RwLock::new(HashMap::new()),
-			packets: RwLock::new(Vec::new())
+			packets: RwLock::new(Vec::new()),
+			last_id: Mutex::new(0)
 		});
 
 		let self_clone = nethandler.clone();
         thread::spawn(move || {
-			let mut last_id: ClientId = 0;
-
 			// Listen for the next client that tries to connect.
 			for stream in listener.incoming() {
 				// Check if the stream is valid and try to create a client for it.
@@ -74,16 +102,7 @@ impl NetHandler {
 					}
 				};
 
-				last_id =  match search_free_id(&self_clone.clients(), last_id+1) {
-					Some(id) => id,
-					None => {
-                        println!("Could not find a free id. Denying client.");
-                        continue;
-                    }
-				};
-
-				// Wrap the stream in a remote and add it to the client map. Then start receiving
-                // for that client.
+				// Wrap the stream in a remote, which will be used by the client from now on.
                 let remote = match Remote::new(stream) {
                     Ok(r) => r,
                     Err(err) => {
@@ -92,21 +111,66 @@ impl NetHandler {
                     }
                 };
 
-				// Create the client from the remote and. The client will then start receiving.
-				let client = NetClient::start(self_clone.clone(), last_id, remote);
+				// The handshake is done on its own thread, so a client that is slow to introduce
+				// itself does not keep anyone else from connecting.
+				let nethandler = self_clone.clone();
+				thread::spawn(move || {
+					nethandler.accept(remote);
+				});
+            }
+        });
 
-				// Add the client to the client map and add a local bus for everyone that only wants
-				// to packets coming from this client.
-                self_clone.clients_mut().insert(last_id, Arc::new(client));
+		nethandler
+    }
 
-                println!("Client connected. ID: {}", last_id);
+    /// Wait for the client on the remote to introduce itself and add it to the clients, if the
+    /// server can talk to it. Otherwise the client is told why and dropped.
+    fn accept(self: Arc<NetHandler>, remote: Remote) {
+		// A client that takes too long to send its Hello is dropped.
+		if let Err(err) = remote.set_timeout(Some(HANDSHAKE_TIMEOUT), DirSocket::Read) {
+			println!("Could not set the handshake timeout of the client. Dropping. {:?}", err);
+			return;
+		}
 
-				// Let the client know which id it will be referred to after this.
-				self_clone.send(last_id, &Packet::ConnectSuccess(last_id));
-            }
-        });
+		let hello = match receive_hello(&remote) {
+			Ok(hello) => hello,
+			Err(reason) => {
+				println!("Rejecting client: {}", reason);
+				remote.write_packet(&Packet::Incompatible(reason));
+				return;
+			}
+		};
 
-		Ok(nethandler)
+		if let Err(err) = remote.set_timeout(None, DirSocket::Read) {
+			println!("Could not reset the read timeout of the client. Dropping. {:?}", err);
+			return;
+		}
+
+		// Only now the client gets an id. The clients are locked while searching, so no two
+		// clients can get the same one.
+		let id = {
+			let mut clients = self.clients_mut();
+			let mut last_id = self.last_id.lock().unwrap();
+			let id = match search_free_id(&clients, *last_id + 1) {
+				Some(id) => id,
+				None => {
+					println!("Could not find a free id. Denying client.");
+					return;
+				}
+			};
+			*last_id = id;
+
+			// Create the client from the remote. The client will then start receiving.
+			let client = NetClient::start(self.clone(), id, remote, hello.capabilities);
+			clients.insert(id, Arc::new(client));
+			id
+		};
+
+		println!("Client connected with protocol version {} and the capabilities {}. ID: {}", hello.version, hello.capabilities, id);
+
+		// Let the client know which id it will be referred to after this and what the server is
+		// capable of.
+		self.send(id, &Packet::Welcome(id, Handshake::new()));
     }
 
     /// Checks, if a client with the id exists and returns true if it does.
@@ -140,6 +204,14 @@ impl NetHandler {
         }
     }
 
+	/// Check if the client has announced all of the capabilities provided in its handshake.
+	pub fn supports(&self, client: ClientId, capabilities: Capabilities) -> bool {
+		match self.clients.read().unwrap().get(&client) {
+			Some(c) => c.capabilities().contains(capabilities),
+			None => false
+		}
+	}
+
 	/// Get a weak reference to the client. Should they disconnect, the parent reference is
 	/// dropped, so it is impossible to predict, how long the client will stay available.
 	pub fn get_client(&self, id: ClientId) -> Option<Weak<NetClient>> {
@@ -201,3 +273,43 @@ impl NetHandler {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::TcpStream;
+
+	fn connect(port: u16) -> Remote {
+		let remote = Remote::new(TcpStream::connect(("127.0.0.1", port)).unwrap()).unwrap();
+		remote.set_timeout(Some(Duration::from_secs(2)), DirSocket::Read).unwrap();
+		remote
+	}
+
+	#[test]
+	fn handshake() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let _nethandler = NetHandler::listen(listener);
+
+		// A client that never says anything must not hold up the others.
+		let _silent = connect(port);
+
+		let client = connect(port);
+		assert!(client.write_packet(&Packet::Hello(Handshake::new())));
+		match client.read_packet() {
+			Ok(Packet::Welcome(_, server)) => assert_eq!(server, Handshake::new()),
+			other => panic!("Expected a Welcome, got {:?}", other)
+		}
+
+		// A client that is too old is told why it is rejected.
+		let old = connect(port);
+		let mut hello = Handshake::new();
+		hello.version = MIN_PROTOCOL_VERSION - 1;
+		hello.min_version = hello.version;
+		assert!(old.write_packet(&Packet::Hello(hello)));
+		match old.read_packet() {
+			Ok(Packet::Incompatible(reason)) => assert!(reason.contains("protocol version")),
+			other => panic!("Expected the client to be rejected, got {:?}", other)
+		}
+	}
+}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Weak, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use std::collections::VecDeque;
+use board::DEFAULT_SIZE;
+use packets::*;
+use rolit::*;
+use square::Square;
+
+use super::NetClient;
+
+/// A game of Rolit between three or four clients. Like a normal Game, it checks every move before
+/// passing it on to the other players.
+pub struct RolitGame {
+    board: Mutex<RolitBoard>,
+    /// The ids of the players in turn order, together with the clients and their packets.
+    players: Vec<(ClientId, Weak<NetClient>, Arc<Mutex<VecDeque<Packet>>>)>,
+	abandoned: AtomicBool
+}
+
+impl RolitGame {
+    /// Create (and start) a new game between the clients provided, which play in the order they
+    /// are in. This will spawn a new thread handling the game, the Weak-pointer will expire when
+    /// the game has ended.
+    pub fn new(clients: Vec<Weak<NetClient>>) -> Option<Weak<RolitGame>> {
+        let mut arcs = Vec::with_capacity(clients.len());
+        for client in &clients {
+            match client.upgrade() {
+                Some(arc) => arcs.push(arc),
+                None => return None
+            }
+        }
+
+        // The game can be started. Tell every player who they are playing with and subscribe to
+        // their packets.
+        let ids: Vec<ClientId> = arcs.iter().map(|arc| { arc.id() }).collect();
+        let mut players = Vec::with_capacity(arcs.len());
+        for (arc, client) in arcs.iter().zip(clients) {
+            arc.send(&Packet::StartRolit(ids.clone()));
+
+            let packets = Arc::new(Mutex::new(VecDeque::new()));
+            arc.subscribe(Arc::downgrade(&packets));
+            players.push((arc.id(), client, packets));
+        }
+
+        let game = Arc::new(RolitGame {
+            board: Mutex::new(RolitBoard::new(DEFAULT_SIZE, ids.len() as u8)),
+            players: players,
+			abandoned: AtomicBool::new(false)
+        });
+
+        let game_weak = Arc::downgrade(&game);
+
+        thread::spawn(move || {
+            while game.is_running() {
+                game.handle_packets();
+
+				thread::sleep(Duration::from_millis(50));
+            }
+
+            game.finish();
+        });
+
+        Some(game_weak)
+    }
+
+    /// The id of the player who has started the game, which the clients use to refer to it.
+    fn host(&self) -> ClientId {
+        self.players[0].0
+    }
+
+    fn handle_packets(&self) {
+        for colour in 0..self.players.len() {
+            loop {
+                let packet = match self.players[colour].2.lock().unwrap().pop_front() {
+                    Some(p) => p,
+                    None => break
+                };
+
+                self.handle_packet(packet, colour as Colour);
+            }
+        }
+    }
+
+    fn handle_packet(&self, packet: Packet, colour: Colour) {
+        let player = self.players[colour as usize].0;
+        match packet {
+            Packet::PlaceRolit(host, x, y) => {
+                // The client might be in other games of Rolit as well.
+                if host != self.host() {
+                    return;
+                }
+
+                let mut board_lock = self.board.lock().unwrap();
+                match board_lock.place((x, y), colour) {
+                    Ok(_) => {
+                        println!("Client [{}] places at {}.", player, Square::from((x, y)));
+                        self.send_others(colour, &Packet::PlaceRolit(player, x, y));
+                    },
+                    Err(err) => println!("[WARNING] Refused move of client [{}] at ({}, {}): {:?}", player, x, y, err)
+                }
+            },
+            Packet::AbandonRolit(host) => {
+                if host != self.host() {
+                    return;
+                }
+
+                self.abandoned.store(true, Ordering::Relaxed);
+                self.send_others(colour, &Packet::AbandonRolit(player));
+            },
+            _ => {}
+        }
+    }
+
+    /// Send the packet to every player except the one with the colour provided.
+    fn send_others(&self, colour: Colour, packet: &Packet) {
+        for (other, &(_, ref client, _)) in self.players.iter().enumerate() {
+            if other != colour as usize {
+                if let Some(client) = client.upgrade() {
+                    client.send(packet);
+                }
+            }
+        }
+    }
+
+    /// Log the result of a finished game, or let the remaining players know that someone has
+    /// disconnected.
+    fn finish(&self) {
+        let board_lock = self.board.lock().unwrap();
+        if board_lock.finished() {
+            let counts: Vec<String> = self.players.iter().enumerate().map(|(colour, &(id, _, _))| {
+                format!("[{}] {}", id, board_lock.count(colour as Colour))
+            }).collect();
+            println!("The game of Rolit has ended with {}.", counts.join(", "));
+            println!("Transcript: {}", board_lock.transcript());
+            return;
+        }
+
+        for (colour, &(id, ref client, _)) in self.players.iter().enumerate() {
+            if client.upgrade().is_none() {
+                self.send_others(colour as Colour, &Packet::AbandonRolit(id));
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        if self.abandoned.load(Ordering::Relaxed) || self.board.lock().unwrap().finished() {
+            return false;
+        }
+
+        // Check that all players are still connected.
+        self.players.iter().all(|&(_, ref client, _)| { client.upgrade().is_some() })
+    }
+}
@@ -0,0 +1,229 @@
+//! Positions other than the standard one that games can start from. They take the well known
+//! openings out of the game, so both players have to think for themselves from the first move on.
+use std::fmt;
+use ai::{AlphaBeta, Limit, Positional};
+use board::{Board, Status};
+use rng::Rng;
+use square::Square;
+
+/// Openings of eight moves in the style of XOT, which a search eight moves deep judges to be
+/// close to even. They are all made on the standard 8x8 board.
+pub const XOT_OPENINGS: [&'static str; 64] = [
+	"e6d6c6d7e8f4f3b6",
+	"e6d6c3f5c4e3f2d3",
+	"c4e3f5g6f3d3c3e6",
+	"f5d6c5b6c3e3c7f4",
+	"c4c5f6d3c3f5d6c6",
+	"c4c3f5f4g3e6d3c5",
+	"f5f6d3g5e6c3g4e3",
+	"e6f6c4c5g6f3f4c3",
+	"d3e3f3c3c4g3f6c5",
+	"f5f4f3d6c3g3d7c7",
+	"f5d6c5f4d7c7e7g5",
+	"d3c3f5d2d1f6f7g5",
+	"c4c5f6f3d6c6f4e3",
+	"c4c3c2b4c5d2a3d6",
+	"e6f4g3g4g5h2f3f2",
+	"d3c5e6f3d6e3e2d2",
+	"d3c5d6e3b4c7e7d7",
+	"e6f6f5f4c3c4g4g6",
+	"f5f4e3d6g4g3c5c4",
+	"f5f4f3d6c6f6c4b6",
+	"f5f4e3d2g3d6c4e6",
+	"c4c5c6e3f5b6f4d6",
+	"f5d6c6f6e6f4g4g6",
+	"f5f6e6f4g3e7f7c6",
+	"e6f4f3f6g4e3f5h4",
+	"e6f6d3d6f5c4e7f8",
+	"f5f6c4f4g5c3g4d6",
+	"e6f6f5d6c4c3f7f4",
+	"f5f4d3d6d7f6e6c4",
+	"f5d6c3f3c5d3e3b4",
+	"e6d6c5f6d7c6c7b6",
+	"f5f6e6f4g6c5c4f7",
+	"f5f6d3c3f7d6b3d2",
+	"c4e3f6e6f4c5c6b4",
+	"e6f4c3c6g3f5f6e7",
+	"e6f6g6d6c3h6e7d3",
+	"d3e3f3c3e6f4f5d6",
+	"d3c5e6f5f6f7b6e3",
+	"e6d6c6d7c7f3e8f6",
+	"e6d6c4f6e7c3d3f8",
+	"c4e3f6e6f7c6d6c5",
+	"c4e3f3c5e6c3d3f5",
+	"d3c3b3f4f5d6g4d2",
+	"e6f6f5f4g4e7d3c4",
+	"e6d6c5f6e7c6f7f3",
+	"c4c3d3c5e6e3c6f5",
+	"f5f4g3c6c4f3e6g6",
+	"d3c5f6f3f4e3d6e6",
+	"e6d6c7f4e3f7g3f2",
+	"f5d6c4b3c5d3c2f6",
+	"d3c3e6d2c2f6d1e3",
+	"e6f6c4c5c6d6f7b6",
+	"c4c5c6b5b6d3b4b3",
+	"d3c5d6c3c4e3f4b5",
+	"e6f4d3d6f5d2c2g5",
+	"c4e3f5c6e6b4b3f6",
+	"c4c5b6d3e2b3b5f3",
+	"f5d6c3f3c5e6g2b2",
+	"e6d6c4f6d7e3f5c5",
+	"c4c3d3e3e2c5d6c6",
+	"c4e3f5c6d6b4b3f6",
+	"c4c5e6c3b6f4d3c6",
+	"f5d6c6f4f3g4e6g6",
+	"c4c3e6d6c5f4g4g3",
+];
+
+/// The most random moves a start position may be made of.
+pub const MAX_RANDOM_MOVES: u8 = 20;
+
+/// The positions a random start may be away from an even game, in the units of the positional
+/// evaluation, unless something else is asked for.
+pub const DEFAULT_WINDOW: u16 = 20;
+
+/// How many random positions are tried before the one closest to the window is taken.
+const RANDOM_ATTEMPTS: usize = 200;
+
+/// The depth of the search judging random positions.
+const RANDOM_DEPTH: u8 = 4;
+
+/// The position a game starts from.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub enum Start {
+	/// The four pieces in the middle of the board.
+	Standard,
+	/// One of the XOT openings, chosen at random when the game is created.
+	Xot,
+	/// The number of random moves is made when the game is created. The position must be
+	/// judged to be within the window of an even game.
+	Random { moves: u8, window: u16 },
+	/// The moves are made before the game starts, which both players need to know.
+	Moves(Vec<(u8, u8)>)
+}
+
+impl Start {
+	/// The moves leading to the start position, or None if they have not been chosen yet.
+	pub fn moves(&self) -> Option<&[(u8, u8)]> {
+		match self {
+			&Start::Standard => Some(&[]),
+			&Start::Moves(ref moves) => Some(moves),
+			_ => None
+		}
+	}
+
+	/// Make the moves of the start position on the board. Returns false if they are not possible
+	/// on it, which leaves the board with some of the moves made.
+	pub fn play(&self, board: &mut Board) -> bool {
+		let moves = match self.moves() {
+			Some(moves) => moves,
+			None => return false
+		};
+
+		for &square in moves {
+			let turn = board.turn();
+			if board.place(square, turn).is_err() {
+				return false;
+			}
+		}
+
+		// There has to be something left to play.
+		match board.status() {
+			Status::Finished { .. } => false,
+			_ => true
+		}
+	}
+
+	/// Check if a game can start from this position on the board provided, which has none of its
+	/// moves made yet. The XOT openings need the standard board.
+	pub fn valid(&self, board: &Board) -> bool {
+		match self {
+			&Start::Standard => true,
+			&Start::Xot => board.size() == 8 && board.blocked() == 0,
+			&Start::Random { moves, .. } => moves <= MAX_RANDOM_MOVES,
+			&Start::Moves(_) => self.play(&mut board.clone())
+		}
+	}
+
+	/// Choose the moves of a start that is decided when the game is created, so everyone can play
+	/// from the same position. Others stay as they are. The start must be valid for the board.
+	pub fn resolve(&self, board: &Board, rng: &mut Rng) -> Start {
+		match self {
+			&Start::Xot => {
+				// Pieces of a handicap might make some of the openings impossible.
+				let playable: Vec<Start> = XOT_OPENINGS.iter().map(|opening| {
+					Start::Moves(opening.as_bytes().chunks(2).map(|m| { (m[0] - b'a', m[1] - b'1') }).collect())
+				}).filter(|start| { start.play(&mut board.clone()) }).collect();
+
+				if playable.is_empty() {
+					return Start::Standard;
+				}
+				playable[rng.below(playable.len() as u64) as usize].clone()
+			},
+			&Start::Random { moves, window } => random_start(board, moves, window, rng),
+			start => start.clone()
+		}
+	}
+}
+
+/// Make random moves on the board until a position is found that is judged to be within the
+/// window. If there is none after a number of attempts, the closest one is taken instead. Every
+/// attempt runs a search of the computer player, so this can take a while and should not be done
+/// on a thread others are waiting for.
+fn random_start(board: &Board, count: u8, window: u16, rng: &mut Rng) -> Start {
+	let mut search = AlphaBeta::new(Positional::new(), Limit::Depth(RANDOM_DEPTH));
+	let mut closest: Option<(i32, Vec<(u8, u8)>)> = None;
+
+	for _ in 0..RANDOM_ATTEMPTS {
+		let mut board = board.clone();
+		let mut moves = Vec::with_capacity(count as usize);
+		while moves.len() < count as usize {
+			let turn = match board.status() {
+				Status::InProgress { to_move } => to_move,
+				_ => break
+			};
+
+			let opportunities = board.opportunities(turn);
+			let square = opportunities[rng.below(opportunities.len() as u64) as usize];
+			board.place(square, turn).unwrap();
+			moves.push(square);
+		}
+
+		// Games that are over already, or close to it, are no good start.
+		if let Status::Finished { .. } = board.status() {
+			continue;
+		}
+
+		let distance = search.search(&board).score.abs();
+		if distance <= window as i32 {
+			return Start::Moves(moves);
+		}
+
+		if closest.as_ref().map_or(true, |&(best, _)| { distance < best }) {
+			closest = Some((distance, moves));
+		}
+	}
+
+	match closest {
+		Some((_, moves)) => Start::Moves(moves),
+		None => Start::Standard
+	}
+}
+
+impl fmt::Display for Start {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			&Start::Standard => write!(f, "the standard position"),
+			&Start::Xot => write!(f, "an XOT opening"),
+			&Start::Random { moves, .. } => write!(f, "{} random moves", moves),
+			&Start::Moves(ref moves) => {
+				write!(f, "the opening ")?;
+				for &square in moves {
+					write!(f, "{}", Square::from(square))?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
@@ -0,0 +1,202 @@
+//! The ways the board can be rotated and reflected without changing the game. Positions that are
+//! the same after one of these, or after swapping the colours together with the player to move,
+//! are equivalent, since the best moves and the final result are the same for both.
+use board::{Bitboard, Board, Piece, bit_square, square_bit};
+
+/// One of the eight symmetries of the square board. The names describe what happens to the board
+/// as it is displayed, with a1 in the upper left corner. The diagonals are named after the 8x8
+/// board, but work the same on boards of other sizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+	Identity,
+	/// Swap the left and the right side.
+	MirrorHorizontal,
+	/// Swap the top and the bottom.
+	MirrorVertical,
+	Rotate180,
+	/// Reflect along the diagonal from a1 to h8.
+	Transpose,
+	/// Rotate by a quarter turn clockwise.
+	Rotate90,
+	/// Rotate by a quarter turn counterclockwise.
+	Rotate270,
+	/// Reflect along the diagonal from h1 to a8.
+	AntiTranspose
+}
+
+/// All symmetries, starting with the identity.
+pub const SYMMETRIES: [Symmetry; 8] = [
+	Symmetry::Identity,
+	Symmetry::MirrorHorizontal,
+	Symmetry::MirrorVertical,
+	Symmetry::Rotate180,
+	Symmetry::Transpose,
+	Symmetry::Rotate90,
+	Symmetry::Rotate270,
+	Symmetry::AntiTranspose
+];
+
+impl Symmetry {
+	/// The symmetry that turns the board back.
+	pub fn inverse(&self) -> Symmetry {
+		match self {
+			&Symmetry::Rotate90 => Symmetry::Rotate270,
+			&Symmetry::Rotate270 => Symmetry::Rotate90,
+			&s => s
+		}
+	}
+
+	/// Where the square ends up on the transformed board with the side length provided. This also
+	/// transforms moves.
+	pub fn square(&self, (x, y): (u8, u8), size: u8) -> (u8, u8) {
+		let last = size - 1;
+		match self {
+			&Symmetry::Identity => (x, y),
+			&Symmetry::MirrorHorizontal => (last - x, y),
+			&Symmetry::MirrorVertical => (x, last - y),
+			&Symmetry::Rotate180 => (last - x, last - y),
+			&Symmetry::Transpose => (y, x),
+			&Symmetry::Rotate90 => (last - y, x),
+			&Symmetry::Rotate270 => (y, last - x),
+			&Symmetry::AntiTranspose => (last - y, last - x)
+		}
+	}
+
+	/// Transform all squares of the bitboard of a board with the side length provided.
+	pub fn bits(&self, mut bits: Bitboard, size: u8) -> Bitboard {
+		if *self == Symmetry::Identity {
+			return bits;
+		}
+
+		let mut transformed = 0;
+		while bits != 0 {
+			transformed |= square_bit(self.square(bit_square(bits.trailing_zeros()), size));
+			bits &= bits - 1;
+		}
+
+		transformed
+	}
+
+	/// The transformed position of the board. The history is not transformed, so the new board
+	/// starts without any.
+	pub fn board(&self, board: &Board) -> Board {
+		let size = board.size();
+		Board::from_pieces(size, self.bits(board.pieces(Piece::Black), size), self.bits(board.pieces(Piece::White), size), board.turn())
+			.with_blocked(self.bits(board.blocked(), size))
+	}
+}
+
+/// The canonical form of a position, which is the same for all positions that are equivalent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Canonical {
+	/// The canonical position. It is always black's turn in it.
+	pub board: Board,
+	/// The symmetry that turns the original board into the canonical one.
+	pub symmetry: Symmetry,
+	/// True if the colours have been swapped, because it was white's turn on the original board.
+	pub swapped: bool
+}
+
+impl Canonical {
+	/// Turn a move on the original board into the same move on the canonical board.
+	pub fn to_canonical(&self, square: (u8, u8)) -> (u8, u8) {
+		self.symmetry.square(square, self.board.size())
+	}
+
+	/// Turn a move on the canonical board back into the move on the original board.
+	pub fn to_original(&self, square: (u8, u8)) -> (u8, u8) {
+		self.symmetry.inverse().square(square, self.board.size())
+	}
+}
+
+/// The canonical pieces of the player to move and their opponent, together with the symmetry
+/// leading to them, on a board with the side length provided. Of all symmetric positions, the one
+/// with the smallest bitboards is chosen.
+pub fn canonical_pieces(own: Bitboard, opp: Bitboard, size: u8) -> ((Bitboard, Bitboard), Symmetry) {
+	let mut best = ((own, opp), Symmetry::Identity);
+	for &s in SYMMETRIES[1..].iter() {
+		let pieces = (s.bits(own, size), s.bits(opp, size));
+		if pieces < best.0 {
+			best = (pieces, s);
+		}
+	}
+
+	best
+}
+
+/// The canonical form of the position on the board. The blocked squares are part of the position,
+/// so they are compared after the pieces.
+pub fn canonical(board: &Board) -> Canonical {
+	let (turn, size) = (board.turn(), board.size());
+	let (own, opp, blocked) = (board.pieces(turn), board.pieces(turn.opposite()), board.blocked());
+
+	let mut best = ((own, opp, blocked), Symmetry::Identity);
+	for &s in SYMMETRIES[1..].iter() {
+		let squares = (s.bits(own, size), s.bits(opp, size), s.bits(blocked, size));
+		if squares < best.0 {
+			best = (squares, s);
+		}
+	}
+
+	let ((own, opp, blocked), symmetry) = best;
+	Canonical {
+		board: Board::from_pieces(size, own, opp, Piece::Black).with_blocked(blocked),
+		symmetry: symmetry,
+		swapped: turn == Piece::White
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use board::{MIN_SIZE, MAX_SIZE};
+
+	/// A position after a few moves with a blocked square, so it has no symmetry of its own.
+	fn position() -> Board {
+		let mut board = Board::new().with_blocked(square_bit((1, 6)));
+		board.replay(&"f5d6c3d3".parse().unwrap()).unwrap();
+		board
+	}
+
+	#[test]
+	fn inverse_undoes_symmetry() {
+		for size in (MIN_SIZE..MAX_SIZE + 1).filter(|size| { size % 2 == 0 }) {
+			for &s in SYMMETRIES.iter() {
+				for x in 0..size {
+					for y in 0..size {
+						assert_eq!(s.inverse().square(s.square((x, y), size), size), (x, y), "{:?} on size {}", s, size);
+					}
+				}
+			}
+		}
+
+		let board = position();
+		for &s in SYMMETRIES.iter() {
+			let back = s.inverse().board(&s.board(&board));
+			assert_eq!(back.to_string(), board.to_string(), "{:?}", s);
+		}
+	}
+
+	#[test]
+	fn symmetric_positions_share_canonical_key() {
+		let board = position();
+		let key = canonical(&board).board.key();
+
+		for &s in SYMMETRIES.iter() {
+			let transformed = s.board(&board);
+			assert_eq!(canonical(&transformed).board.key(), key, "{:?}", s);
+
+			// The same position with the colours and the player to move swapped.
+			let swapped = Board::from_pieces(8, transformed.pieces(Piece::White), transformed.pieces(Piece::Black), transformed.turn().opposite())
+				.with_blocked(transformed.blocked());
+			assert_eq!(canonical(&swapped).board.key(), key, "{:?} swapped", s);
+		}
+
+		// Moves are carried over to the canonical board and back.
+		let c = canonical(&board);
+		for square in board.opportunities(board.turn()) {
+			assert!(c.board.can_place(c.to_canonical(square), Piece::Black));
+			assert_eq!(c.to_original(c.to_canonical(square)), square);
+		}
+	}
+}
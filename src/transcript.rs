@@ -0,0 +1,107 @@
+//! Records of entire games in the usual notation, where all moves are written one after the other,
+//! for instance "f5d6c3d3c4". A pass is written as "--".
+use std::fmt;
+use std::str::FromStr;
+use square::{self, Square};
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+	/// The move with the index could not be read.
+	InvalidMove(usize, square::ParseError),
+	/// The character at the byte offset cannot start a move.
+	UnexpectedChar(usize, char)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+	/// The move with the index could not be made on the board.
+	IllegalMove(usize, Square),
+	/// The player could not pass at the index, because they could still place a piece.
+	IllegalPass(usize)
+}
+
+/// All moves of a game in the order they have been made. A pass is represented by None.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript {
+	moves: Vec<Option<Square>>
+}
+
+impl Transcript {
+	pub fn new(moves: Vec<Option<Square>>) -> Transcript {
+		Transcript {
+			moves: moves
+		}
+	}
+
+	pub fn moves(&self) -> &[Option<Square>] {
+		&self.moves
+	}
+
+	pub fn len(&self) -> usize {
+		self.moves.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.moves.is_empty()
+	}
+}
+
+impl fmt::Display for Transcript {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for m in &self.moves {
+			match m {
+				&Some(ref square) => write!(f, "{}", square)?,
+				&None => write!(f, "--")?
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl FromStr for Transcript {
+	type Err = ParseError;
+
+	/// Read the transcript. Whitespace between the moves is ignored and the columns may be written
+	/// in upper or lower case.
+	fn from_str(s: &str) -> Result<Transcript, ParseError> {
+		let mut moves = Vec::new();
+		let mut chars = s.char_indices().peekable();
+
+		while let Some((i, c)) = chars.next() {
+			if c.is_whitespace() {
+				continue;
+			}
+
+			if c == '-' {
+				match chars.next() {
+					Some((_, '-')) => moves.push(None),
+					_ => return Err(ParseError::UnexpectedChar(i, c))
+				}
+				continue;
+			}
+
+			if !c.is_ascii_alphabetic() {
+				return Err(ParseError::UnexpectedChar(i, c));
+			}
+
+			// The row is made up of all digits directly following the column.
+			let mut end = i + c.len_utf8();
+			while let Some(&(j, d)) = chars.peek() {
+				if !d.is_ascii_digit() {
+					break;
+				}
+
+				end = j + d.len_utf8();
+				chars.next();
+			}
+
+			match s[i..end].parse() {
+				Ok(square) => moves.push(Some(square)),
+				Err(err) => return Err(ParseError::InvalidMove(moves.len(), err))
+			}
+		}
+
+		Ok(Transcript::new(moves))
+	}
+}